@@ -1,17 +1,15 @@
 use std::f64;
 use DisplayPoint;
+use font::{SPACE_BETWEEN_CHARS, W_CHAR};
+use layout::{Rect, H_ARROW_HALF, W_ARROW};
 use tick;
 
 const W_POINT: u8 = 1;      //value point separator width
-const W_BORDER: usize = 1;     //space around graph width
-const W_DECIMAL_SEPARATOR: usize = 1;     //space between numbers in pixels
-const W_ARROW: usize = 4;      //width of arrow
-const W_NUMBER: usize = 4;     //number width in pixel
-const H_NUMBER: usize = 5;     //number height in pixels
 const MAX_INTERVALS: u8 = 10;   // maximum intervals count
-const START_SHIFT: usize = W_BORDER + H_NUMBER + W_NUMBER;
+const MAX_DECIMAL_PLACES: u8 = 15;     //about the precision of f64
 const DEFAULT_SIZE: usize = 100;
 
+/// Axis values and their placement along an axis `size` pixels long.
 #[derive(Debug, Clone)]
 pub struct Axis {
     pub min_value: f64,
@@ -30,43 +28,69 @@ impl Axis {
         Axis { rotated: true, ..self }
     }
 
-    pub fn create_points(&self) -> Vec<DisplayPoint> {
-        let mut v: Vec<DisplayPoint> = vec![];
-        let ticks = self.create_ticks_points();
-        let line = self.calculate_axis_line();
-        let arrow = self.calculate_axis_arrow();
-        v.extend(ticks);
-        v.extend(line);
-        v.extend(arrow);
-        if self.rotated {
-            v.into_iter()
-                .map(|p| DisplayPoint { x: p.y, y: p.x })
-                .collect::<Vec<DisplayPoint>>()
+    /// Axis line, arrow, ticks and labels along the left (rotated axis) or
+    /// bottom edge of `plot`.
+    pub fn create_points(&self, plot: &Rect) -> Vec<DisplayPoint> {
+        let (start, axis_position) = if self.rotated {
+            (plot.y, plot.x)
         } else {
-            v
-        }
+            (plot.x, plot.y)
+        };
+        let rotated = self.rotated;
+        let place = move |(along, across): (usize, isize)| {
+            let across = (axis_position as isize + across) as usize;
+            if rotated {
+                DisplayPoint { x: across, y: along }
+            } else {
+                DisplayPoint { x: along, y: across }
+            }
+        };
+        let mut v: Vec<DisplayPoint> = vec![];
+        v.extend(self.calculate_axis_line(start).into_iter().map(&place));
+        v.extend(self.calculate_axis_arrow(start + self.size + W_ARROW).into_iter().map(&place));
+        v.extend(self.create_ticks_points(start, axis_position));
+        v
     }
 
-    pub fn set_axis_auto(max: f64, min: f64, total_size: usize) -> Axis {
-        let available_size = total_size - 2 * W_BORDER - H_NUMBER - W_NUMBER - W_ARROW;
-        let (s_max, decimal_places) = determine_max_numbers_count(max, min);
-        let interval_count = calculate_intervals_count(available_size, s_max);
-        let scale_interval_pix = (available_size as f64) / (interval_count as f64);
-        let min_value = calc(f64::floor, min, decimal_places as i32);
-        let max_value = calc(f64::ceil, max, decimal_places as i32);
-        let scale_interval_value = (max_value - min_value) / (interval_count as f64);
-        let scale_interval_value = calc(f64::ceil, scale_interval_value, decimal_places as i32);
+    /// Labels of every tick, from the minimum value to the maximum one.
+    pub fn labels(&self) -> Vec<String> {
+        (0..(self.interval_count as usize + 1))
+            .map(|i| {
+                let value = self.min_value + self.scale_interval_value * (i as f64);
+                round(value, self.decimal_places as i32).to_string()
+            })
+            .collect()
+    }
 
-        Axis {
-            min_value: min_value,
-            max_value: max_value,
-            scale_interval_value: scale_interval_value,
-            scale_interval_pix: scale_interval_pix,
-            interval_count: interval_count,
-            decimal_places: decimal_places,
-            size: total_size,
-            rotated: false,
-        }
+    /// Same axis values placed along an axis `size` pixels long.
+    pub fn fit(&self, size: usize) -> Axis {
+        let axis = Axis::set_axis_manual(self.min_value,
+                                         self.max_value,
+                                         self.interval_count,
+                                         self.decimal_places,
+                                         size);
+        Axis { rotated: self.rotated, ..axis }
+    }
+
+    pub fn set_axis_auto(max: f64, min: f64, size: usize) -> Axis {
+        // a single value, or values closer than the decimal places go, get
+        // an axis around them, no values or infinite ones get a unit one
+        let finest = 10f64.powi(1 - MAX_DECIMAL_PLACES as i32);
+        let (max, min) = if min.is_finite() && max.is_finite() && max - min >= finest {
+            (max, min)
+        } else if min.is_finite() && max.is_finite() && min <= max {
+            let margin = (min.abs() * 1e-6).max(1f64);
+            (max + margin, min - margin)
+        } else {
+            (1f64, -1f64)
+        };
+        let (s_max, decimal_places) = determine_max_numbers_count(max, min);
+        let interval_count = calculate_intervals_count(size, s_max);
+        let axis = Axis::set_axis_manual(min, max, interval_count, decimal_places, size);
+        // stretch the axis so the last tick lands on its maximum
+        let max_value = round(axis.min_value + axis.scale_interval_value * (interval_count as f64),
+                              decimal_places as i32);
+        Axis { max_value, ..axis }
     }
 
 
@@ -76,8 +100,7 @@ impl Axis {
                            decimal_places: u8,
                            size: usize)
                            -> Axis {
-        let available_size = size - 2 * W_BORDER - H_NUMBER - W_NUMBER - W_ARROW;
-        let scale_interval_pix = (available_size as f64) / (interval_count as f64);
+        let scale_interval_pix = (size as f64) / (interval_count as f64);
         let min = calc(f64::floor, min_value, decimal_places as i32);
         let max = calc(f64::ceil, max_value, decimal_places as i32);
         let mut scale_interval_value = (max - min) / (interval_count as f64);
@@ -86,11 +109,11 @@ impl Axis {
         Axis {
             min_value: min,
             max_value: max,
-            scale_interval_value: scale_interval_value,
-            scale_interval_pix: scale_interval_pix,
-            interval_count: interval_count,
-            decimal_places: decimal_places,
-            size: size,
+            scale_interval_value,
+            scale_interval_pix,
+            interval_count,
+            decimal_places,
+            size,
             rotated: false,
         }
     }
@@ -108,39 +131,27 @@ impl Axis {
         }
     }
 
-    fn create_ticks_points(&self) -> Vec<DisplayPoint> {
+    fn create_ticks_points(&self, start: usize, axis_position: usize) -> Vec<DisplayPoint> {
         let mut v: Vec<DisplayPoint> = vec![];
-        for i in 0..self.interval_count {
-            let value = round((self.min_value + self.scale_interval_value * (i as f64)),
-                              self.decimal_places as i32);
-            let value_s = &*value.to_string();
+        for (i, value_s) in self.labels().iter().enumerate() {
             let shift = (self.scale_interval_pix * (i as f64)).round() as usize;
-            v.extend(tick::create_tick_with_label(START_SHIFT + shift, value_s, self.rotated));
+            v.extend(tick::create_tick_with_label(start + shift,
+                                                  axis_position,
+                                                  value_s,
+                                                  self.rotated));
         }
         v
     }
 
-    fn calculate_axis_line(&self) -> Vec<DisplayPoint> {
-        let mut v = vec![];
-        for x in START_SHIFT..self.size {
-            v.push(DisplayPoint {
-                x: x,
-                y: START_SHIFT,
-            });
-        }
-        v
+    // (along, across) offsets, `across` being relative to the axis line
+    fn calculate_axis_line(&self, start: usize) -> Vec<(usize, isize)> {
+        (start..(start + self.size + W_ARROW + 1)).map(|along| (along, 0)).collect()
     }
 
 
-    fn calculate_axis_arrow(&self) -> Vec<DisplayPoint> {
-        vec![(4, 13), (3, 12), (2, 11), (4, 7), (3, 8), (2, 9)]
-            .into_iter()
-            .map(move |(x, y)| {
-                DisplayPoint {
-                    x: self.size - x,
-                    y: y,
-                }
-            })
+    fn calculate_axis_arrow(&self, end: usize) -> Vec<(usize, isize)> {
+        (1..H_ARROW_HALF + 1)
+            .flat_map(|i| vec![(end - i, i as isize), (end - i, -(i as isize))])
             .collect()
     }
 }
@@ -155,7 +166,8 @@ fn calc<F>(f: F, value: f64, decimal_places: i32) -> f64
     where F: Fn(f64) -> f64
 {
     let k = 10f64.powi(decimal_places);
-    let new_value = f(value * k);
+    // float noise, as in 1.1 * 10 = 11.000000000000002, is no step up or down
+    let new_value = f(round(value * k, 9));
     new_value / k
 }
 
@@ -170,7 +182,7 @@ fn determine_max_numbers_count(max: f64, min: f64) -> (u8, u8) {
         (maxc, 0)
     } else {
         let mut decimal_places = 0;
-        while d < 10.0 {
+        while d < 10.0 && decimal_places < MAX_DECIMAL_PLACES {
             d *= 10.0;
             decimal_places += 1;
         }
@@ -183,9 +195,11 @@ fn get_numbers_count(value: i64) -> u8 {
 }
 
 fn calculate_intervals_count(available_size: usize, s_max: u8) -> u8 {
-    let k = (available_size / ((W_NUMBER + W_DECIMAL_SEPARATOR) * (s_max as usize))) - 1;
+    let k = (available_size / ((W_CHAR + SPACE_BETWEEN_CHARS) * (s_max as usize))).saturating_sub(1);
     if k > MAX_INTERVALS as usize {
         MAX_INTERVALS
+    } else if k == 0 {
+        1
     } else {
         k as u8
     }
@@ -237,18 +251,45 @@ mod tests {
         let interval_count = axis::calculate_intervals_count(width, s_max);
         assert_eq!(interval_count, 10);
     }
+
+    #[test]
+    fn degenerate_range_test() {
+        let axis = axis::Axis::set_axis_auto(3.0, 3.0, 200);
+        assert!(axis.min_value <= 2.0 && axis.max_value >= 4.0);
+        let axis = axis::Axis::set_axis_auto(f64::NEG_INFINITY, f64::INFINITY, 200);
+        assert!(axis.min_value <= -1.0 && axis.max_value >= 1.0);
+        let axis = axis::Axis::set_axis_auto(1e-300, 0.0, 200);
+        assert!(axis.max_value > axis.min_value);
+    }
+
+    #[test]
+    fn labels_span_whole_axis_test() {
+        let axis = axis::Axis::set_axis_manual(-2.0, 2.0, 4, 1, 200);
+        assert_eq!(axis.labels(), vec!["-2", "-1", "0", "1", "2"]);
+        assert_eq!(axis.scale_interval_pix, 50.0);
+    }
+
+    #[test]
+    fn fit_keeps_values_test() {
+        let axis = axis::Axis::set_axis_auto(1.05, 0.0, 300);
+        let fitted = axis.fit(200);
+        assert_eq!(fitted.labels(), axis.labels());
+        assert_eq!(fitted.max_value, axis.max_value);
+        assert_eq!(fitted.scale_interval_pix, 200.0 / (axis.interval_count as f64));
+    }
 }
 
 #[cfg(all(feature = "dev", test))]
 mod bench {
     extern crate test;
     use super::*;
+    use layout::Rect;
 
     #[bench]
     fn create_axis_bench(b: &mut test::Bencher) {
         b.iter(|| {
             let axis = Axis::set_axis_auto(100.0, 0.0, 1000);
-            let _ = axis.create_points();
+            let _ = axis.create_points(&Rect::new(10, 10, 1000, 1000));
         })
     }
 }
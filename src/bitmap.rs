@@ -1,16 +1,39 @@
-use byteorder::{LittleEndian, WriteBytesExt};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::str::FromStr;
+
+use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
+
+use chart::GraphError;
 
 const HEADER_LENGHT: u32 = 14;
 const INFO_LENGHT: u32 = 124;
 const COLOR_SIZE: u32 = 4;
+const COLOR_COUNT: u32 = 256;
 const RESERVED: u8 = 0;
 
+const BI_RGB: u32 = 0;
+const BI_RLE8: u32 = 1;
+const BI_RLE4: u32 = 2;
+const BI_BITFIELDS: u32 = 3;
+const BI_ALPHABITFIELDS: u32 = 6;
+const CUBE_LEVELS: u32 = 6;     //levels of each channel when colors are reduced
+const MIN_RUN: usize = 3;     //shorter runs are written as they are
+const MAX_RUN: usize = 255;
+const MAX_PIXELS: usize = 1 << 28;     //largest run length encoded picture read
+const PBM: u8 = 1;     //magic numbers of plain Netpbm files
+const PGM: u8 = 2;
+const PPM: u8 = 3;
+const PLAIN_LINE: usize = 70;     //longest line of a plain Netpbm file
+
 #[derive(Debug)]
 pub struct BitMap {
-    header: BitMapHeader,
-    info: BitMapInfo,
-    color_table: ColorTable,
+    width: usize,
+    height: usize,
+    palette: Vec<Color>,
     array: Vec<u8>,
+    compression: Compression,
 }
 
 impl Default for BitMap {
@@ -21,17 +44,18 @@ impl Default for BitMap {
 
 impl BitMap {
     pub fn new(width: usize, height: usize) -> Self {
-        let mut b = BitMap {
-            header: BitMapHeader::new(),
-            info: BitMapInfo::new(),
-            color_table: ColorTable::new(),
+        BitMap {
+            width,
+            height,
+            palette: vec![],
             array: vec![],
-        };
-
-        b.info.set_width(width as i32);
-        b.info.set_height(height as i32);
+            compression: Compression::None,
+        }
+    }
 
-        b
+    /// Bmp files get written with `compression`, none at first.
+    pub fn set_compression(&mut self, compression: Compression) {
+        self.compression = compression;
     }
 
     pub fn add_pixels(&mut self, pic: &[u8]) {
@@ -41,27 +65,666 @@ impl BitMap {
     pub fn add_color<C>(&mut self, color: C) -> u8
         where C: Into<Color>
     {
-        let color = color.into();
-        self.color_table.add_color(color);
+        self.palette.push(color.into());
+        (self.palette.len() - 1) as u8
+    }
+
+    /// Writes the bmp file, failing if there are not `width * height`
+    /// pixels or more than 256 colors.
+    pub fn write_to<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut encoder = BmpEncoder::with_compression(writer,
+                                                       self.width,
+                                                       self.height,
+                                                       &self.palette,
+                                                       self.compression)?;
+        if self.width > 0 {
+            for row in self.array.chunks(self.width) {
+                encoder.write_row(row)?;
+            }
+        }
+        encoder.finish().map(|_| ())
+    }
+
+    pub fn as_vec(&self) -> Vec<u8> {
+        let mut bitmap = vec![];
+        self.write_to(&mut bitmap).expect("bmp pixels must fill the whole picture");
+        bitmap
+    }
 
-        self.info.clr_used += 1;
-        (self.info.clr_used - 1) as u8
+    /// Writes the picture as a Netpbm file of the simplest kind showing it:
+    /// PBM if every color is black or white, PGM if they are grey, else PPM.
+    pub fn write_netpbm<W: Write>(&self, writer: W, encoding: NetpbmEncoding) -> io::Result<()> {
+        let black = Color { r: 0, g: 0, b: 0 };
+        let white = Color { r: 255, g: 255, b: 255 };
+        let kind = if self.palette.iter().all(|c| *c == black || *c == white) {
+            PBM
+        } else if self.palette.iter().all(|c| c.r == c.g && c.g == c.b) {
+            PGM
+        } else {
+            PPM
+        };
+        self.write_pnm(writer, kind, encoding)
     }
 
-    pub fn as_vec(&mut self) -> Vec<u8> {
-        let mut bitmap = vec![]; // V5
+    /// Writes the picture as a PPM file, P6 or P3 for the plain encoding.
+    pub fn write_ppm<W: Write>(&self, writer: W, encoding: NetpbmEncoding) -> io::Result<()> {
+        self.write_pnm(writer, PPM, encoding)
+    }
 
-        let pixels_data_offset = HEADER_LENGHT + INFO_LENGHT + self.color_table.get_size();
-        self.header.set_data_offset(pixels_data_offset);
+    fn write_pnm<W: Write>(&self, mut writer: W, kind: u8, encoding: NetpbmEncoding) -> io::Result<()> {
+        if self.array.len() != self.width * self.height ||
+           self.array.iter().any(|&p| p as usize >= self.palette.len()) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "pixels don't fill the picture with colors of the palette"));
+        }
+        let magic = match encoding {
+            NetpbmEncoding::Binary => kind + 3,
+            NetpbmEncoding::Plain => kind,
+        };
+        write!(writer, "P{}\n{} {}\n", magic, self.width, self.height)?;
+        if kind != PBM {
+            writer.write_all(b"255\n")?;
+        }
 
-        let file_lenght = pixels_data_offset + self.array.len() as u32;
-        self.header.set_lenght(file_lenght);
+        let mut samples = vec![];
+        // Netpbm rows start from the top
+        for row in self.array.chunks(self.width.max(1)).rev() {
+            samples.clear();
+            for &p in row {
+                let color = self.palette[p as usize];
+                match kind {
+                    // 1 is black
+                    PBM => samples.push(if color.r == 0 { 1 } else { 0 }),
+                    PGM => samples.push(color.r),
+                    _ => samples.extend_from_slice(&[color.r, color.g, color.b]),
+                }
+            }
+            match encoding {
+                NetpbmEncoding::Binary if kind == PBM => {
+                    for bits in samples.chunks(8) {
+                        let byte = bits.iter().enumerate().fold(0u8, |byte, (i, &b)| byte | b << (7 - i));
+                        writer.write_all(&[byte])?;
+                    }
+                }
+                NetpbmEncoding::Binary => writer.write_all(&samples)?,
+                NetpbmEncoding::Plain => write_plain_row(&samples, &mut writer)?,
+            }
+        }
+        writer.flush()
+    }
 
-        bitmap.extend_from_slice(&self.header.to_vec());
-        bitmap.extend_from_slice(&self.info.to_vec());
-        bitmap.extend_from_slice(&self.color_table.to_vec());
-        bitmap.extend_from_slice(&self.array);
-        bitmap
+    /// Reads a bmp file of 1, 4, 8, 16, 24 or 32 bits per pixel, uncompressed,
+    /// RLE or bit fields, with a core, V3, V4 or V5 info header.
+    ///
+    /// Pictures without a color table get one of their own colors, those of
+    /// more than 256 colors are reduced to a 6x6x6 color cube first.
+    pub fn read<R: Read>(mut reader: R) -> Result<BitMap, GraphError> {
+        let mut data = vec![];
+        reader.read_to_end(&mut data)?;
+        decode(&data)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn palette(&self) -> &[Color] {
+        &self.palette
+    }
+
+    /// Palette indices row by row, the bottom row first.
+    pub fn pixels(&self) -> &[u8] {
+        &self.array
+    }
+}
+
+
+fn decode(data: &[u8]) -> Result<BitMap, GraphError> {
+    if !data.starts_with(b"BM") {
+        return Err(invalid("not a bmp file"));
+    }
+    let offset = read_u32(data, 10)? as usize;
+    let info_size = read_u32(data, 14)? as usize;
+
+    let (width, height, bitcount, compression, clr_used, entry_size) = match info_size {
+        12 => {
+            (read_u16(data, 18)? as i32,
+             read_u16(data, 20)? as i32,
+             read_u16(data, 24)?,
+             BI_RGB,
+             0,
+             3)
+        }
+        40 | 52 | 56 | 108 | 124 => {
+            (read_u32(data, 18)? as i32,
+             read_u32(data, 22)? as i32,
+             read_u16(data, 28)?,
+             read_u32(data, 30)?,
+             read_u32(data, 46)? as usize,
+             4)
+        }
+        size => return Err(invalid(format!("unknown info header of {} bytes", size))),
+    };
+
+    let supported = match compression {
+        BI_RGB => [1, 4, 8, 16, 24, 32].contains(&bitcount),
+        BI_RLE8 => bitcount == 8 && height > 0,
+        BI_RLE4 => bitcount == 4 && height > 0,
+        BI_BITFIELDS | BI_ALPHABITFIELDS => bitcount == 16 || bitcount == 32,
+        _ => false,
+    };
+    if !supported || width <= 0 || height == 0 {
+        return Err(invalid(format!("{}x{} picture of {} bits per pixel, compression {}, \
+                                    is not supported",
+                                   width,
+                                   height,
+                                   bitcount,
+                                   compression)));
+    }
+    // rows of negative height pictures go from the top
+    let top_down = height < 0;
+    let (width, height) = (width as usize, height.unsigned_abs() as usize);
+
+    // masks of a V3 header follow it, those of later headers are part of them
+    let masks_size = match (info_size, compression) {
+        (40, BI_BITFIELDS) => 12,
+        (40, BI_ALPHABITFIELDS) => 16,
+        _ => 0,
+    };
+    let masks = if compression == BI_BITFIELDS || compression == BI_ALPHABITFIELDS {
+        [read_u32(data, 54)?, read_u32(data, 58)?, read_u32(data, 62)?]
+    } else if bitcount == 16 {
+        [0x7c00, 0x03e0, 0x001f]
+    } else {
+        [0x00ff_0000, 0x0000_ff00, 0x0000_00ff]
+    };
+
+    if bitcount > 8 {
+        let colors = decode_true_color(data, offset, width, height, bitcount, masks)?;
+        let (palette, array) = index_colors(&colors, width, height, top_down);
+        return Ok(BitMap {
+            width,
+            height,
+            palette,
+            array,
+            compression: Compression::None,
+        });
+    }
+
+    let entries = if clr_used > 0 {
+        clr_used.min(COLOR_COUNT as usize)
+    } else {
+        1 << bitcount
+    };
+    let table = 14 + info_size + masks_size;
+    let palette = (0..entries)
+        .map(|i| {
+            let entry = table + i * entry_size;
+            Ok(Color {
+                r: read_u8(data, entry + 2)?,
+                g: read_u8(data, entry + 1)?,
+                b: read_u8(data, entry)?,
+            })
+        })
+        .collect::<Result<Vec<Color>, GraphError>>()?;
+
+    let array = match compression {
+        BI_RLE8 | BI_RLE4 => decode_rle(data, offset, width, height, compression == BI_RLE4)?,
+        _ => {
+            let row_size = (width * bitcount as usize).div_ceil(32) * 4;
+            if row_size.checked_mul(height).and_then(|size| size.checked_add(offset)).is_none_or(|end| data.len() < end) {
+                return Err(invalid("unexpected end of file"));
+            }
+            let mut array = vec![0u8; width * height];
+            for r in 0..height {
+                let y = if top_down { height - 1 - r } else { r };
+                let row = offset + r * row_size;
+                for x in 0..width {
+                    let bit = x * bitcount as usize;
+                    let byte = read_u8(data, row + bit / 8)?;
+                    let shift = 8 - bitcount as usize - bit % 8;
+                    array[y * width + x] = (byte >> shift) & (0xff >> (8 - bitcount));
+                }
+            }
+            array
+        }
+    };
+    if array.iter().any(|&p| p as usize >= palette.len()) {
+        return Err(invalid("pixel color is not in the color table"));
+    }
+
+    Ok(BitMap {
+        width,
+        height,
+        palette,
+        array,
+        compression: match compression {
+            BI_RLE4 | BI_RLE8 => Compression::Rle,
+            _ => Compression::None,
+        },
+    })
+}
+
+fn decode_true_color(data: &[u8],
+                     offset: usize,
+                     width: usize,
+                     height: usize,
+                     bitcount: u16,
+                     masks: [u32; 3])
+                     -> Result<Vec<Color>, GraphError> {
+    let bytes = bitcount as usize / 8;
+    let row_size = (width * bytes).div_ceil(4) * 4;
+    if row_size.checked_mul(height).and_then(|size| size.checked_add(offset)).is_none_or(|end| data.len() < end) {
+        return Err(invalid("unexpected end of file"));
+    }
+    let mut colors = Vec::with_capacity(width * height);
+    for r in 0..height {
+        for x in 0..width {
+            let pixel = offset + r * row_size + x * bytes;
+            let color = if bitcount == 24 {
+                Color {
+                    r: read_u8(data, pixel + 2)?,
+                    g: read_u8(data, pixel + 1)?,
+                    b: read_u8(data, pixel)?,
+                }
+            } else {
+                let value = if bitcount == 16 {
+                    read_u16(data, pixel)? as u32
+                } else {
+                    read_u32(data, pixel)?
+                };
+                Color {
+                    r: channel(value, masks[0]),
+                    g: channel(value, masks[1]),
+                    b: channel(value, masks[2]),
+                }
+            };
+            colors.push(color);
+        }
+    }
+    Ok(colors)
+}
+
+// value of the channel under `mask` scaled to 0..255
+fn channel(value: u32, mask: u32) -> u8 {
+    if mask == 0 {
+        return 0;
+    }
+    let shift = mask.trailing_zeros();
+    let max = (mask >> shift) as u64;
+    let level = ((value & mask) >> shift) as u64;
+    ((level * 255 + max / 2) / max) as u8
+}
+
+// rows of `colors` go in file order
+fn index_colors(colors: &[Color], width: usize, height: usize, top_down: bool) -> (Vec<Color>, Vec<u8>) {
+    let mut indices: HashMap<Color, u8> = HashMap::new();
+    let mut palette = vec![];
+    for color in colors {
+        if !indices.contains_key(color) {
+            if palette.len() == COLOR_COUNT as usize {
+                let reduced = colors.iter().map(|c| reduce(*c)).collect::<Vec<Color>>();
+                return index_colors(&reduced, width, height, top_down);
+            }
+            indices.insert(*color, palette.len() as u8);
+            palette.push(*color);
+        }
+    }
+
+    let mut array = vec![0u8; width * height];
+    for (r, row) in colors.chunks(width).enumerate() {
+        let y = if top_down { height - 1 - r } else { r };
+        for (x, color) in row.iter().enumerate() {
+            array[y * width + x] = indices[color];
+        }
+    }
+    (palette, array)
+}
+
+// nearest color of the color cube
+fn reduce(color: Color) -> Color {
+    let level = |c: u8| {
+        let step = 255 / (CUBE_LEVELS - 1);
+        (((c as u32 + step / 2) / step) * step) as u8
+    };
+    Color {
+        r: level(color.r),
+        g: level(color.g),
+        b: level(color.b),
+    }
+}
+
+// runs of a color, or absolute pixels after a 0 count, in rows from the bottom
+fn decode_rle(data: &[u8],
+              offset: usize,
+              width: usize,
+              height: usize,
+              rle4: bool)
+              -> Result<Vec<u8>, GraphError> {
+    // a delta of 4 bytes skips at most 255 rows, the missing end of bitmap
+    // forgiven below can't make up for more
+    let remaining = data.len().saturating_sub(offset);
+    let size = width.checked_mul(height).filter(|&size| size <= MAX_PIXELS);
+    if size.is_none() || height > (remaining / 4 + 1) * MAX_RUN {
+        return Err(invalid(format!("{}x{} picture is too large for its data", width, height)));
+    }
+    let mut array = vec![0u8; width * height];
+    let (mut x, mut y) = (0, 0);
+    let mut pos = offset;
+    let pixel = |byte: u8, k: usize| if !rle4 {
+        byte
+    } else if k.is_multiple_of(2) {
+        byte >> 4
+    } else {
+        byte & 0x0f
+    };
+
+    // missing end of bitmap is forgiven
+    while pos + 1 < data.len() && y < height {
+        let (count, value) = (data[pos] as usize, data[pos + 1]);
+        pos += 2;
+        match (count, value) {
+            (0, 0) => {
+                x = 0;
+                y += 1;
+            }
+            (0, 1) => break,
+            (0, 2) => {
+                x += read_u8(data, pos)? as usize;
+                y += read_u8(data, pos + 1)? as usize;
+                pos += 2;
+            }
+            (0, n) => {
+                let n = n as usize;
+                for k in 0..n {
+                    let byte = read_u8(data, pos + if rle4 { k / 2 } else { k })?;
+                    if x < width && y < height {
+                        array[y * width + x] = pixel(byte, k);
+                    }
+                    x += 1;
+                }
+                // absolute runs are padded to 16 bits
+                let bytes = if rle4 { n.div_ceil(2) } else { n };
+                pos += bytes + bytes % 2;
+            }
+            (count, value) => {
+                for k in 0..count {
+                    if x < width && y < height {
+                        array[y * width + x] = pixel(value, k);
+                    }
+                    x += 1;
+                }
+            }
+        }
+    }
+    Ok(array)
+}
+
+fn invalid<S: Into<String>>(message: S) -> GraphError {
+    GraphError::InvalidBitmap(message.into())
+}
+
+fn read_u8(data: &[u8], pos: usize) -> Result<u8, GraphError> {
+    data.get(pos).cloned().ok_or_else(|| invalid("unexpected end of file"))
+}
+
+fn read_u16(data: &[u8], pos: usize) -> Result<u16, GraphError> {
+    match data.get(pos..pos + 2) {
+        Some(bytes) => Ok(LittleEndian::read_u16(bytes)),
+        None => Err(invalid("unexpected end of file")),
+    }
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Result<u32, GraphError> {
+    match data.get(pos..pos + 4) {
+        Some(bytes) => Ok(LittleEndian::read_u32(bytes)),
+        None => Err(invalid("unexpected end of file")),
+    }
+}
+
+
+/// How values of a Netpbm file are written.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NetpbmEncoding {
+    /// Bytes, or bits for PBM files.
+    Binary,
+    /// Decimal numbers separated by spaces, easy to read and diff.
+    Plain,
+}
+
+// lines of a row no longer than `PLAIN_LINE`, the row ending one of them
+fn write_plain_row<W: Write>(samples: &[u8], writer: &mut W) -> io::Result<()> {
+    let mut line = String::new();
+    for sample in samples {
+        let sample = sample.to_string();
+        if !line.is_empty() && line.len() + 1 + sample.len() > PLAIN_LINE {
+            writeln!(writer, "{}", line)?;
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&sample);
+    }
+    writeln!(writer, "{}", line)
+}
+
+
+/// How rows of a bmp file are stored.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    /// Every pixel, 8 bits each.
+    None,
+    /// Runs of one color, 4 bits RLE for palettes of 16 colors or less and
+    /// 8 bits RLE for bigger ones.
+    Rle,
+}
+
+/// Writes a bmp file row by row, so the picture is never kept in memory
+/// whole. Rows are palette indices, the bottom row goes first.
+///
+/// Compressed rows are kept until `finish` though, since the headers
+/// written before them hold their size.
+#[derive(Debug)]
+pub struct BmpEncoder<W: Write> {
+    writer: W,
+    width: usize,
+    height: usize,
+    rows: usize,
+    palette: Vec<Color>,
+    compression: u32,
+    bitcount: u16,
+    encoded: Vec<u8>,
+}
+
+impl<W: Write> BmpEncoder<W> {
+    /// Writes the headers and the color table of a `width` x `height`
+    /// picture of at most 256 colors. Pixels take 1, 4 or 8 bits, the fewest
+    /// the palette fits in.
+    pub fn new(writer: W, width: usize, height: usize, palette: &[Color]) -> io::Result<Self> {
+        Self::with_compression(writer, width, height, palette, Compression::None)
+    }
+
+    pub fn with_compression(mut writer: W,
+                            width: usize,
+                            height: usize,
+                            palette: &[Color],
+                            compression: Compression)
+                            -> io::Result<Self> {
+        if palette.len() > COLOR_COUNT as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "bmp picture can't have more than 256 colors"));
+        }
+        let compression = match compression {
+            Compression::None => BI_RGB,
+            Compression::Rle if palette.len() <= 16 => BI_RLE4,
+            Compression::Rle => BI_RLE8,
+        };
+        let bitcount = match compression {
+            BI_RLE4 => 4,
+            BI_RLE8 => 8,
+            _ => bit_depth(palette.len()),
+        };
+
+        if compression == BI_RGB {
+            let sizeimage = row_length(width, bitcount) * height;
+            write_headers(&mut writer, width, height, palette, compression, bitcount, sizeimage)?;
+        }
+
+        Ok(BmpEncoder {
+            writer,
+            width,
+            height,
+            rows: 0,
+            palette: palette.to_vec(),
+            compression,
+            bitcount,
+            encoded: vec![],
+        })
+    }
+
+    pub fn write_row(&mut self, row: &[u8]) -> io::Result<()> {
+        if row.len() != self.width || self.rows == self.height {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "row doesn't fit the bmp picture"));
+        }
+        match self.compression {
+            BI_RGB => {
+                pack_row(row, self.bitcount, &mut self.encoded);
+                self.writer.write_all(&self.encoded)?;
+            }
+            compression => encode_rle_row(row, compression == BI_RLE4, &mut self.encoded),
+        }
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// Gives the writer back once every row is written.
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.rows != self.height {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "bmp picture lacks some rows"));
+        }
+        if self.compression != BI_RGB {
+            // end of bitmap
+            self.encoded.extend_from_slice(&[0, 1]);
+            write_headers(&mut self.writer,
+                          self.width,
+                          self.height,
+                          &self.palette,
+                          self.compression,
+                          self.bitcount,
+                          self.encoded.len())?;
+            self.writer.write_all(&self.encoded)?;
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+fn write_headers<W: Write>(writer: &mut W,
+                           width: usize,
+                           height: usize,
+                           palette: &[Color],
+                           compression: u32,
+                           bitcount: u16,
+                           sizeimage: usize)
+                           -> io::Result<()> {
+    // no more colors than the palette has, a table can't be empty though
+    let colors = palette.len().max(1) as u32;
+    let offset = HEADER_LENGHT + INFO_LENGHT + colors * COLOR_SIZE;
+
+    let mut header = BitMapHeader::new();
+    header.set_data_offset(offset);
+    header.set_lenght(offset + sizeimage as u32);
+
+    let mut info = BitMapInfo::new();
+    info.set_width(width as i32);
+    info.set_height(height as i32);
+    info.bitcount = bitcount;
+    info.compression = compression;
+    info.sizeimage = sizeimage as u32;
+    info.clr_used = colors;
+
+    header.write_to(writer)?;
+    info.write_to(writer)?;
+    write_color_table(palette, writer)
+}
+
+// runs of 3 pixels or more are encoded, the pixels between them are written
+// as they are, then the end of line
+fn encode_rle_row(row: &[u8], rle4: bool, out: &mut Vec<u8>) {
+    let run_length = |pixels: &[u8]| {
+        pixels.iter().take(MAX_RUN).take_while(|&&p| p == pixels[0]).count()
+    };
+    let mut i = 0;
+    while i < row.len() {
+        let run = run_length(&row[i..]);
+        if run >= MIN_RUN {
+            let pixel = if rle4 { row[i] << 4 | row[i] } else { row[i] };
+            out.extend_from_slice(&[run as u8, pixel]);
+            i += run;
+            continue;
+        }
+
+        let mut end = i + 1;
+        while end < row.len() && end - i < MAX_RUN && run_length(&row[end..]) < MIN_RUN {
+            end += 1;
+        }
+        let pixels = &row[i..end];
+        if pixels.len() < MIN_RUN {
+            // absolute mode needs 3 pixels at least
+            for &p in pixels {
+                out.extend_from_slice(&[1, if rle4 { p << 4 } else { p }]);
+            }
+        } else {
+            out.extend_from_slice(&[0, pixels.len() as u8]);
+            let start = out.len();
+            if rle4 {
+                for pair in pixels.chunks(2) {
+                    out.push(pair[0] << 4 | pair.get(1).cloned().unwrap_or(0));
+                }
+            } else {
+                out.extend_from_slice(pixels);
+            }
+            // padded to 16 bits
+            if (out.len() - start) % 2 == 1 {
+                out.push(0);
+            }
+        }
+        i = end;
+    }
+    // end of line
+    out.extend_from_slice(&[0, 0]);
+}
+
+// bits per pixel of a palette of `colors`
+fn bit_depth(colors: usize) -> u16 {
+    match colors {
+        0..=2 => 1,
+        3..=16 => 4,
+        _ => 8,
+    }
+}
+
+// rows are padded to whole 4 byte words
+fn row_length(width: usize, bitcount: u16) -> usize {
+    (width * bitcount as usize).div_ceil(32) * 4
+}
+
+// pixels of a row, the leftmost ones in the high bits of a byte
+fn pack_row(row: &[u8], bitcount: u16, out: &mut Vec<u8>) {
+    out.clear();
+    out.resize(row_length(row.len(), bitcount), 0);
+    let per_byte = 8 / bitcount as usize;
+    let mask = (0xffu16 >> (8 - bitcount)) as u8;
+    for (x, &p) in row.iter().enumerate() {
+        let shift = bitcount as usize * (per_byte - 1 - x % per_byte);
+        out[x / per_byte] |= (p & mask) << shift;
     }
 }
 
@@ -92,13 +755,11 @@ impl BitMapHeader {
         self.f_off_bitsfield = offset;
     }
 
-    fn to_vec(&self) -> Vec<u8> {
-        let mut header = vec![]; // V5
-        header.write_u16::<LittleEndian>(self.little_indian).unwrap();
-        header.write_u32::<LittleEndian>(self.file_length).unwrap();
-        header.write_u32::<LittleEndian>(self.reserved).unwrap();
-        header.write_u32::<LittleEndian>(self.f_off_bitsfield).unwrap();
-        header
+    fn write_to<W: Write>(&self, header: &mut W) -> io::Result<()> {
+        header.write_u16::<LittleEndian>(self.little_indian)?;
+        header.write_u32::<LittleEndian>(self.file_length)?;
+        header.write_u32::<LittleEndian>(self.reserved)?;
+        header.write_u32::<LittleEndian>(self.f_off_bitsfield)
     }
 }
 
@@ -167,38 +828,37 @@ impl BitMapInfo {
         }
     }
 
-    fn to_vec(&self) -> Vec<u8> {
-        let mut bmp_info = vec![]; // V5
-        bmp_info.write_u32::<LittleEndian>(self.size).unwrap(); // size
-        bmp_info.write_i32::<LittleEndian>(self.width).unwrap(); // width
-        bmp_info.write_i32::<LittleEndian>(self.height).unwrap(); // height
-        bmp_info.write_u16::<LittleEndian>(self.planes).unwrap(); // planes
-        bmp_info.write_u16::<LittleEndian>(self.bitcount).unwrap(); // bitcount 32
-        bmp_info.write_u32::<LittleEndian>(self.compression).unwrap(); // compression 0 - BI_RGB
-        bmp_info.write_u32::<LittleEndian>(self.sizeimage).unwrap(); // sizeimage
-        bmp_info.write_i32::<LittleEndian>(self.xpels_per_meter).unwrap(); // XpelsPerMeter
-        bmp_info.write_i32::<LittleEndian>(self.ypels_per_meter).unwrap(); // YpelsPerMeter
-        bmp_info.write_u32::<LittleEndian>(self.clr_used).unwrap(); // ClrUsed
-        bmp_info.write_u32::<LittleEndian>(self.clr_important).unwrap(); // ClrImportant
+    fn write_to<W: Write>(&self, bmp_info: &mut W) -> io::Result<()> {
+        bmp_info.write_u32::<LittleEndian>(self.size)?; // size
+        bmp_info.write_i32::<LittleEndian>(self.width)?; // width
+        bmp_info.write_i32::<LittleEndian>(self.height)?; // height
+        bmp_info.write_u16::<LittleEndian>(self.planes)?; // planes
+        bmp_info.write_u16::<LittleEndian>(self.bitcount)?; // bitcount 32
+        bmp_info.write_u32::<LittleEndian>(self.compression)?; // compression 0 - BI_RGB
+        bmp_info.write_u32::<LittleEndian>(self.sizeimage)?; // sizeimage
+        bmp_info.write_i32::<LittleEndian>(self.xpels_per_meter)?; // XpelsPerMeter
+        bmp_info.write_i32::<LittleEndian>(self.ypels_per_meter)?; // YpelsPerMeter
+        bmp_info.write_u32::<LittleEndian>(self.clr_used)?; // ClrUsed
+        bmp_info.write_u32::<LittleEndian>(self.clr_important)?; // ClrImportant
 
-        bmp_info.write_u32::<LittleEndian>(self.red_mask).unwrap(); // RedMask
-        bmp_info.write_u32::<LittleEndian>(self.green_mask).unwrap(); // GreenMask
-        bmp_info.write_u32::<LittleEndian>(self.blue_mask).unwrap(); // BlueMask
-        bmp_info.write_u32::<LittleEndian>(self.alpha_mask).unwrap(); // AlphaMask
-        bmp_info.write_u32::<LittleEndian>(self.c_stype).unwrap(); // CSType
+        bmp_info.write_u32::<LittleEndian>(self.red_mask)?; // RedMask
+        bmp_info.write_u32::<LittleEndian>(self.green_mask)?; // GreenMask
+        bmp_info.write_u32::<LittleEndian>(self.blue_mask)?; // BlueMask
+        bmp_info.write_u32::<LittleEndian>(self.alpha_mask)?; // AlphaMask
+        bmp_info.write_u32::<LittleEndian>(self.c_stype)?; // CSType
 
-        bmp_info.extend_from_slice(&self.c_iexyztriple);                  // CIEXYZTRIPLE
+        bmp_info.write_all(&self.c_iexyztriple)?;                  // CIEXYZTRIPLE
 
-        bmp_info.write_u32::<LittleEndian>(self.gamma_red).unwrap(); // GammaRed
-        bmp_info.write_u32::<LittleEndian>(self.gamma_green).unwrap(); // GammaGreen
-        bmp_info.write_u32::<LittleEndian>(self.gamma_blue).unwrap(); // GammaBlue
+        bmp_info.write_u32::<LittleEndian>(self.gamma_red)?; // GammaRed
+        bmp_info.write_u32::<LittleEndian>(self.gamma_green)?; // GammaGreen
+        bmp_info.write_u32::<LittleEndian>(self.gamma_blue)?; // GammaBlue
 
-        bmp_info.write_u32::<LittleEndian>(self.intent).unwrap(); // Intent
-        bmp_info.write_u32::<LittleEndian>(self.profile_data).unwrap(); // ProfileData
-        bmp_info.write_u32::<LittleEndian>(self.profile_size).unwrap(); // ProfileSize
-        bmp_info.write_u32::<LittleEndian>(self.reserved).unwrap(); // Reserved
+        bmp_info.write_u32::<LittleEndian>(self.intent)?; // Intent
+        bmp_info.write_u32::<LittleEndian>(self.profile_data)?; // ProfileData
+        bmp_info.write_u32::<LittleEndian>(self.profile_size)?; // ProfileSize
+        bmp_info.write_u32::<LittleEndian>(self.reserved)?; // Reserved
 
-        bmp_info
+        Ok(())
     }
 
     fn set_width(&mut self, width: i32) {
@@ -210,40 +870,18 @@ impl BitMapInfo {
     }
 }
 
-#[derive(Debug)]
-struct ColorTable {
-    count: u32,
-    table: Vec<u8>,
-}
-
-impl ColorTable {
-    fn new() -> ColorTable {
-        ColorTable {
-            count: 0,
-            table: vec![],
-        }
+// a zeroed color stands in for an empty palette
+fn write_color_table<W: Write>(palette: &[Color], writer: &mut W) -> io::Result<()> {
+    for color in palette {
+        writer.write_all(&color.get_buffer())?;
     }
-
-    fn add_color(&mut self, color: Color) {
-        self.count += 1;
-        self.table.append(&mut color.get_buffer());
-    }
-
-    fn get_size(&self) -> u32 {
-        256 * COLOR_SIZE
-    }
-
-    fn to_vec(&self) -> Vec<u8> {
-        let mut v: Vec<u8> = vec![];
-        v.extend_from_slice(&self.table);
-        for _ in self.count..256 {
-            v.write_u32::<LittleEndian>(0).unwrap();
-        }
-        v
+    if palette.is_empty() {
+        writer.write_u32::<LittleEndian>(0)?;
     }
+    Ok(())
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
@@ -251,8 +889,30 @@ pub struct Color {
 }
 
 impl Color {
-    fn get_buffer(&self) -> Vec<u8> {
-        vec![self.b, self.g, self.r, RESERVED]
+    fn get_buffer(&self) -> [u8; 4] {
+        [self.b, self.g, self.r, RESERVED]
+    }
+}
+
+// #ffaabb
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+// #ffaabb
+impl FromStr for Color {
+    type Err = GraphError;
+
+    fn from_str(string: &str) -> Result<Color, GraphError> {
+        let valid = string.len() == 7 && string.starts_with('#') &&
+                    string[1..].chars().all(|c| c.is_ascii_hexdigit());
+        if valid {
+            Ok(Color::from(string))
+        } else {
+            Err(GraphError::InvalidColor(string.to_string()))
+        }
     }
 }
 
@@ -266,3 +926,214 @@ impl<'a> From<&'a str> for Color {
         Color { r: r, g: g, b: b }
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_are_padded_test() {
+        let palette = [Color::from("#ffffff"), Color::from("#ff0000"), Color::from("#00ff00")];
+        let mut encoder = BmpEncoder::new(vec![], 9, 2, &palette).unwrap();
+        encoder.write_row(&[1, 0, 0, 0, 1, 2, 2, 0, 1]).unwrap();
+        encoder.write_row(&[0, 1, 1, 1, 0, 0, 0, 0, 0]).unwrap();
+        let bmp = encoder.finish().unwrap();
+
+        let offset = (HEADER_LENGHT + INFO_LENGHT + 3 * COLOR_SIZE) as usize;
+        assert_eq!(bmp.len(), offset + 16);
+        assert_eq!(&bmp[2..6], &[(offset + 16) as u8, 0, 0, 0][..]);
+        assert_eq!(&bmp[offset..],
+                   &[0x10, 0x00, 0x12, 0x20, 0x10, 0x00, 0x00, 0x00, 0x01, 0x11, 0x00, 0x00, 0x00,
+                     0x00, 0x00, 0x00][..]);
+    }
+
+    #[test]
+    fn bit_depth_follows_palette_test() {
+        for &(colors, bitcount) in &[(2u8, 1u16), (16, 4), (17, 8)] {
+            let mut bitmap = BitMap::new(10, 3);
+            for i in 0..colors {
+                bitmap.add_color(Color { r: 0, g: i, b: 0 });
+            }
+            let pixels = (0..30).map(|i| i % colors).collect::<Vec<u8>>();
+            bitmap.add_pixels(&pixels);
+
+            let bmp = bitmap.as_vec();
+            assert_eq!(LittleEndian::read_u16(&bmp[28..30]), bitcount);
+            // colors used, and the table of them
+            assert_eq!(LittleEndian::read_u32(&bmp[46..50]), colors as u32);
+            assert_eq!(LittleEndian::read_u32(&bmp[10..14]), 138 + 4 * colors as u32);
+            let read = BitMap::read(&bmp[..]).unwrap();
+            assert_eq!(read.palette(), bitmap.palette());
+            assert_eq!(read.pixels(), &pixels[..]);
+        }
+    }
+
+    #[test]
+    fn wrong_rows_are_errors_test() {
+        let mut encoder = BmpEncoder::new(vec![], 3, 2, &[Color::from("#ffffff")]).unwrap();
+        assert!(encoder.write_row(&[0, 0]).is_err());
+        encoder.write_row(&[0, 0, 0]).unwrap();
+        assert!(encoder.finish().is_err());
+
+        let mut bitmap = BitMap::new(3, 1);
+        bitmap.add_color("#ffffff");
+        bitmap.add_pixels(&[0, 0, 0, 0]);
+        assert!(bitmap.write_to(vec![]).is_err());
+    }
+
+    // V3 headers, a color table of `palette` and `pixels` right after it
+    fn bmp(width: i32, height: i32, bitcount: u16, compression: u32, palette: &[u8], pixels: &[u8]) -> Vec<u8> {
+        let offset = 54 + palette.len() as u32;
+        let mut bmp = b"BM".to_vec();
+        for &value in &[offset + pixels.len() as u32, 0, offset, 40, width as u32, height as u32] {
+            bmp.write_u32::<LittleEndian>(value).unwrap();
+        }
+        bmp.write_u16::<LittleEndian>(1).unwrap();
+        bmp.write_u16::<LittleEndian>(bitcount).unwrap();
+        for &value in &[compression, pixels.len() as u32, 0, 0, palette.len() as u32 / 4, 0] {
+            bmp.write_u32::<LittleEndian>(value).unwrap();
+        }
+        bmp.extend_from_slice(palette);
+        bmp.extend_from_slice(pixels);
+        bmp
+    }
+
+    #[test]
+    fn read_written_bitmap_test() {
+        let mut bitmap = BitMap::new(3, 2);
+        bitmap.add_color("#ffffff");
+        bitmap.add_color("#102030");
+        bitmap.add_pixels(&[0, 1, 0, 1, 1, 0]);
+
+        let read = BitMap::read(&bitmap.as_vec()[..]).unwrap();
+        assert_eq!((read.width(), read.height()), (3, 2));
+        assert_eq!(read.palette(), bitmap.palette());
+        assert_eq!(read.pixels(), bitmap.pixels());
+    }
+
+    #[test]
+    fn read_packed_and_true_color_test() {
+        // 1 bit, rows of 4 bytes with the leftmost pixel in the high bit
+        let palette = [0, 0, 0, 0, 255, 255, 255, 0];
+        let bitmap = BitMap::read(&bmp(3, 2, 1, BI_RGB, &palette, &[0xa0, 0, 0, 0, 0x40, 0, 0, 0])[..])
+            .unwrap();
+        assert_eq!(bitmap.pixels(), &[1, 0, 1, 0, 1, 0][..]);
+
+        // 24 bits from the top row, blue green red in rows padded to 4 bytes
+        let pixels = [0, 0, 255, 0, 0, 255, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        let bitmap = BitMap::read(&bmp(2, -2, 24, BI_RGB, &[], &pixels)[..]).unwrap();
+        assert_eq!(bitmap.palette(), &[Color::from("#ff0000"), Color::from("#000000")][..]);
+        assert_eq!(bitmap.pixels(), &[1, 1, 0, 0][..]);
+    }
+
+    #[test]
+    fn read_rle_test() {
+        let palette = [0, 0, 0, 0, 255, 255, 255, 0, 0, 0, 255, 0];
+        // run of 3 whites, end of line, delta of 1 right, absolute 2 reds and white
+        let pixels = [3, 1, 0, 0, 0, 2, 1, 0, 0, 3, 2, 2, 1, 0, 0, 1];
+        let bitmap = BitMap::read(&bmp(4, 2, 8, BI_RLE8, &palette, &pixels)[..]).unwrap();
+        assert_eq!(bitmap.pixels(), &[1, 1, 1, 0, 0, 2, 2, 1][..]);
+
+        let pixels = [5, 0x12, 0, 1];
+        let bitmap = BitMap::read(&bmp(6, 1, 4, BI_RLE4, &palette, &pixels)[..]).unwrap();
+        assert_eq!(bitmap.pixels(), &[1, 2, 1, 2, 1, 0][..]);
+    }
+
+    #[test]
+    fn read_invalid_bitmap_test() {
+        let error = BitMap::read(&b"GIF89a"[..]).err().unwrap();
+        assert_eq!(error.to_string(), "Bitmap can't be read: not a bmp file");
+        let truncated = bmp(2, 2, 24, BI_RGB, &[], &[0; 10]);
+        let error = BitMap::read(&truncated[..]).err().unwrap();
+        assert_eq!(error.to_string(), "Bitmap can't be read: unexpected end of file");
+
+        let huge = bmp(0x7fff_ffff, 0x7fff_ffff, 24, BI_RGB, &[], &[0; 10]);
+        let error = BitMap::read(&huge[..]).err().unwrap();
+        assert_eq!(error.to_string(), "Bitmap can't be read: unexpected end of file");
+        let huge = bmp(0x7fff_ffff, 0x7fff_ffff, 8, BI_RLE8, &[0; 4], &[0, 1]);
+        let error = BitMap::read(&huge[..]).err().unwrap();
+        assert_eq!(error.to_string(),
+                   "Bitmap can't be read: 2147483647x2147483647 picture is too large for its data");
+        let tall = bmp(1, 1_000_000, 8, BI_RLE8, &[0; 4], &[0, 1]);
+        assert!(BitMap::read(&tall[..]).is_err());
+    }
+
+
+    #[test]
+    fn rle_row_test() {
+        let mut out = vec![];
+        encode_rle_row(&[5, 5, 5, 5, 1, 2, 3, 4, 4, 4, 1, 2], false, &mut out);
+        assert_eq!(out, vec![4, 5, 0, 3, 1, 2, 3, 0, 3, 4, 1, 1, 1, 2, 0, 0]);
+        let mut out = vec![];
+        encode_rle_row(&[1, 2, 3, 6, 6, 6], true, &mut out);
+        assert_eq!(out, vec![0, 3, 0x12, 0x30, 3, 0x66, 0, 0]);
+    }
+
+    #[test]
+    fn read_written_rle_test() {
+        for &colors in &[3, 20] {
+            let mut bitmap = BitMap::new(300, 3);
+            for i in 0..colors {
+                bitmap.add_color(Color { r: i * 10, g: 0, b: 0 });
+            }
+            let pixels = (0..900)
+                .map(|i| if i % 7 == 0 { (i % colors as usize) as u8 } else { 1 })
+                .collect::<Vec<u8>>();
+            bitmap.add_pixels(&pixels);
+            bitmap.set_compression(Compression::Rle);
+
+            let bmp = bitmap.as_vec();
+            let compression = if colors <= 16 { BI_RLE4 } else { BI_RLE8 };
+            assert_eq!(LittleEndian::read_u32(&bmp[30..34]), compression);
+            assert_eq!(LittleEndian::read_u32(&bmp[2..6]) as usize, bmp.len());
+            assert_eq!(BitMap::read(&bmp[..]).unwrap().pixels(), &pixels[..]);
+        }
+    }
+
+
+    fn netpbm(bitmap: &BitMap, encoding: NetpbmEncoding) -> Vec<u8> {
+        let mut out = vec![];
+        bitmap.write_netpbm(&mut out, encoding).unwrap();
+        out
+    }
+
+    #[test]
+    fn netpbm_kind_follows_colors_test() {
+        let mut bitmap = BitMap::new(10, 2);
+        bitmap.add_color("#ffffff");
+        bitmap.add_color("#000000");
+        bitmap.add_pixels(&[1, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        // the top row first, bits padded to whole bytes
+        assert_eq!(netpbm(&bitmap, NetpbmEncoding::Binary), b"P4\n10 2\n\x00\x00\x80\x40");
+        assert_eq!(netpbm(&bitmap, NetpbmEncoding::Plain),
+                   &b"P1\n10 2\n0 0 0 0 0 0 0 0 0 0\n1 0 0 0 0 0 0 0 0 1\n"[..]);
+
+        bitmap.add_color("#808080");
+        assert!(netpbm(&bitmap, NetpbmEncoding::Binary).starts_with(b"P5\n10 2\n255\n"));
+        bitmap.add_color("#ff0000");
+        assert!(netpbm(&bitmap, NetpbmEncoding::Binary).starts_with(b"P6\n10 2\n255\n"));
+    }
+
+    #[test]
+    fn ppm_test() {
+        let mut bitmap = BitMap::new(2, 1);
+        bitmap.add_color("#ffffff");
+        bitmap.add_color("#102030");
+        bitmap.add_pixels(&[1, 0]);
+
+        let mut out = vec![];
+        bitmap.write_ppm(&mut out, NetpbmEncoding::Binary).unwrap();
+        assert_eq!(out, b"P6\n2 1\n255\n\x10\x20\x30\xff\xff\xff");
+
+        let mut bitmap = BitMap::new(30, 1);
+        bitmap.add_color("#808080");
+        bitmap.add_pixels(&[0; 30]);
+        let mut out = vec![];
+        bitmap.write_ppm(&mut out, NetpbmEncoding::Plain).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.lines().all(|line| line.len() <= PLAIN_LINE));
+        assert_eq!(text.matches("128").count(), 90);
+    }
+
+}
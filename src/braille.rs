@@ -0,0 +1,323 @@
+use std::char;
+use std::f64;
+use std::vec;
+
+use Axis;
+use Chart;
+use Color;
+use Coordinates;
+use Serie;
+use chart::{GraphError, InPoint, IterInPoint, Points, to_cartesian};
+use line;
+
+const DOTS_X: usize = 2;     //braille dots in a character cell
+const DOTS_Y: usize = 4;
+const MIN_COLUMNS: usize = 4;     //smallest plot in characters
+const MIN_ROWS: usize = 2;
+const LAYOUT_PASSES: usize = 2;     // y labels width depends on plot size and back
+
+// bits of the dots in a cell by their column and row, the top row first
+const DOT_BITS: [[u32; DOTS_Y]; DOTS_X] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+const BRAILLE_BLANK: u32 = 0x2800;
+
+
+/// Escape sequences coloring series in the terminal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnsiColors {
+    None,
+    /// The 6x6x6 color cube of 256 color terminals.
+    Palette256,
+    /// 24 bit colors.
+    TrueColor,
+}
+
+/// Renders a chart as text of Unicode braille characters, each holding
+/// 2 x 4 dots, for terminals which can't show pictures.
+///
+/// Axes are calculated the same way `Chart::draw` does, with labels of
+/// the ticks along the left and bottom edges. Polar charts are drawn with
+/// their points converted to x and y.
+#[derive(Debug, Clone, Copy)]
+pub struct Braille {
+    columns: usize,
+    rows: usize,
+    colors: AnsiColors,
+}
+
+impl Braille {
+    /// Text `columns` characters wide and `rows` lines high, titles,
+    /// labels and legend included.
+    pub fn new(columns: usize, rows: usize) -> Braille {
+        Braille {
+            columns,
+            rows,
+            colors: AnsiColors::TrueColor,
+        }
+    }
+
+    pub fn colors(self, colors: AnsiColors) -> Braille {
+        Braille { colors, ..self }
+    }
+
+    pub fn render<S, T, P>(&self, chart: &Chart, series: S) -> Result<String, GraphError>
+        where S: Iterator<Item = Serie<T, P>>,
+              T: IterInPoint<P>,
+              P: InPoint
+    {
+        let series = if chart.coordinates() == Coordinates::Polar {
+            series.filter_map(|s| to_cartesian(&s)).collect::<Vec<Points>>()
+        } else {
+            series.map(|s| s.collect_points()).collect::<Vec<Points>>()
+        };
+        let legend = series.iter().filter_map(|s| s.name().map(|n| (n, s.color()))).collect::<Vec<_>>();
+
+        let top_rows = chart.title().iter().count() + chart.axis_y_title().iter().count();
+        let bottom_rows = 2 + chart.axis_x_title().iter().count() + legend.len();
+        let plot_rows = self.rows.saturating_sub(top_rows + bottom_rows);
+        if plot_rows < MIN_ROWS {
+            return Err(GraphError::NotEnoughSpace);
+        }
+
+        let fit = |label_width: usize| {
+            let plot_columns = self.columns.saturating_sub(label_width + 1);
+            if plot_columns < MIN_COLUMNS {
+                return Err(GraphError::NotEnoughSpace);
+            }
+            let (axis_x, axis_y) = chart.fit_cartesian_axes(&series,
+                                                            plot_columns * DOTS_X - 1,
+                                                            plot_rows * DOTS_Y - 1);
+            Ok((plot_columns, axis_x, axis_y))
+        };
+        let mut label_width = 0;
+        for _ in 0..LAYOUT_PASSES {
+            let (_, _, axis_y) = fit(label_width)?;
+            label_width = axis_y.labels().iter().map(|l| l.chars().count()).max().unwrap_or(0);
+        }
+        let (plot_columns, axis_x, axis_y) = fit(label_width)?;
+
+        let mut cells = Cells::new(plot_columns, plot_rows);
+        for (i, serie) in series.iter().enumerate() {
+            cells.draw_serie(serie, &axis_x, &axis_y, i);
+        }
+
+        let colors = series.iter().map(|s| self.escape(Color::from(s.color()))).collect::<Vec<String>>();
+
+        let mut text = String::new();
+        if let Some(title) = chart.title() {
+            text.push_str(&centered(title, label_width + 1, plot_columns));
+        }
+        if let Some(title) = chart.axis_y_title() {
+            text.push_str(title);
+            text.push('\n');
+        }
+
+        let ticks_y = tick_cells(&axis_y, DOTS_Y);
+        let labels_y = axis_y.labels();
+        for row in 0..plot_rows {
+            let row_from_bottom = plot_rows - 1 - row;
+            match ticks_y.iter().position(|&r| r == row_from_bottom) {
+                Some(i) => {
+                    text.push_str(&format!("{:>1$}┤", labels_y[i], label_width));
+                }
+                None => {
+                    text.push_str(&format!("{:>1$}│", "", label_width));
+                }
+            }
+            text.push_str(&cells.row_text(row, &colors, self.reset()));
+            text.push('\n');
+        }
+
+        let ticks_x = tick_cells(&axis_x, DOTS_X);
+        text.push_str(&format!("{:>1$}└", "", label_width));
+        for column in 0..plot_columns {
+            text.push(if ticks_x.contains(&column) { '┬' } else { '─' });
+        }
+        text.push('\n');
+        text.push_str(&x_labels(&axis_x.labels(), &ticks_x, label_width + 1, self.columns));
+        text.push('\n');
+
+        if let Some(title) = chart.axis_x_title() {
+            text.push_str(&centered(title, label_width + 1, plot_columns));
+        }
+        for &(name, color) in &legend {
+            text.push_str(&format!("{}──{} {}\n", self.escape(Color::from(color)), self.reset(), name));
+        }
+        Ok(text)
+    }
+
+    fn escape(&self, color: Color) -> String {
+        match self.colors {
+            AnsiColors::None => String::new(),
+            AnsiColors::Palette256 => {
+                let level = |c: u8| ((c as f64) / 255f64 * 5f64).round() as u32;
+                format!("\x1b[38;5;{}m",
+                        16 + 36 * level(color.r) + 6 * level(color.g) + level(color.b))
+            }
+            AnsiColors::TrueColor => format!("\x1b[38;2;{};{};{}m", color.r, color.g, color.b),
+        }
+    }
+
+    fn reset(&self) -> &'static str {
+        match self.colors {
+            AnsiColors::None => "",
+            _ => "\x1b[0m",
+        }
+    }
+}
+
+
+// dots of the plot grouped in character cells, the top row first
+struct Cells {
+    columns: usize,
+    rows: usize,
+    bits: Vec<u32>,
+    series: Vec<Option<usize>>,
+}
+
+impl Cells {
+    fn new(columns: usize, rows: usize) -> Cells {
+        Cells {
+            columns,
+            rows,
+            bits: vec![0; columns * rows],
+            series: vec![None; columns * rows],
+        }
+    }
+
+    fn draw_serie(&mut self, serie: &Points, axis_x: &Axis, axis_y: &Axis, index: usize) {
+        let (width, height) = (self.columns * DOTS_X, self.rows * DOTS_Y);
+        let place = |value: f64, axis: &Axis, size: usize| {
+            (value - axis.min_value) / (axis.max_value - axis.min_value) * ((size - 1) as f64)
+        };
+        let dots = serie.iter.clone().map(|p| (place(p.x, axis_x, width), place(p.y, axis_y, height)));
+        for dot in line::clip_polyline(dots, width - 1, height - 1) {
+            let (x, y) = (dot.x, height - 1 - dot.y);
+            let cell = (y / DOTS_Y) * self.columns + x / DOTS_X;
+            self.bits[cell] |= DOT_BITS[x % DOTS_X][y % DOTS_Y];
+            self.series[cell] = Some(index);
+        }
+    }
+
+    fn row_text(&self, row: usize, colors: &[String], reset: &str) -> String {
+        let mut text = String::new();
+        let mut current = None;
+        for cell in row * self.columns..(row + 1) * self.columns {
+            if self.bits[cell] == 0 {
+                text.push(' ');
+                continue;
+            }
+            if self.series[cell] != current {
+                current = self.series[cell];
+                text.push_str(&colors[current.unwrap()]);
+            }
+            text.push(char::from_u32(BRAILLE_BLANK + self.bits[cell]).unwrap());
+        }
+        if current.is_some() {
+            text.push_str(reset);
+        }
+        text
+    }
+}
+
+
+// cells of the axis ticks, counted from the start of the axis
+fn tick_cells(axis: &Axis, dots: usize) -> Vec<usize> {
+    (0..(axis.interval_count as usize + 1))
+        .map(|i| (axis.scale_interval_pix * (i as f64)).round() as usize / dots)
+        .collect()
+}
+
+// labels centered under their ticks, those which would overlap or not fit
+// left out
+fn x_labels(labels: &[String], ticks: &[usize], offset: usize, columns: usize) -> String {
+    let mut line = vec![' '; columns];
+    let mut free_from = 0;
+    for (label, &tick) in labels.iter().zip(ticks) {
+        let width = label.chars().count();
+        if width > columns {
+            continue;
+        }
+        let start = (offset + tick).saturating_sub(width / 2);
+        let start = if start + width > columns {
+            columns.saturating_sub(width)
+        } else {
+            start
+        };
+        if start < free_from {
+            continue;
+        }
+        for (i, c) in label.chars().enumerate() {
+            line[start + i] = c;
+        }
+        free_from = start + width + 1;
+    }
+    line.into_iter().collect::<String>().trim_end().to_string()
+}
+
+fn centered(text: &str, offset: usize, width: usize) -> String {
+    let shift = offset + width.saturating_sub(text.chars().count()) / 2;
+    format!("{:1$}{2}\n", "", shift, text)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Point;
+
+    fn chart() -> Chart {
+        Chart::new(100, 100, "#ffffff", "#000000").unwrap()
+    }
+
+    fn serie() -> Points {
+        let points = vec![Point { x: 0f64, y: 0f64 }, Point { x: 10f64, y: 10f64 }];
+        Serie::new(points.into_iter(), "#ff0000").unwrap().add_name("line")
+    }
+
+    #[test]
+    fn diagonal_line_test() {
+        let text = Braille::new(20, 10)
+            .colors(AnsiColors::None)
+            .render(&chart().add_title("T"), vec![serie()].into_iter())
+            .unwrap();
+        let lines = text.lines().collect::<Vec<&str>>();
+        assert_eq!(lines.len(), 10);
+        assert_eq!(lines[0].trim(), "T");
+        assert_eq!(lines.last().unwrap(), &"── line");
+        assert!(lines.iter().all(|l| l.chars().count() <= 20));
+
+        // the line goes from the bottom left corner to the top right one
+        let plot = &lines[1..7];
+        let first = plot.first().unwrap().trim_end();
+        let last = plot.last().unwrap();
+        assert!(first.ends_with(|c: char| ('\u{2801}'..='\u{28ff}').contains(&c)));
+        let corner = last.chars().position(|c| c == '┤' || c == '│').unwrap() + 1;
+        assert!(last.chars().nth(corner).unwrap() >= '\u{2801}');
+        assert!(lines[7].contains('└'));
+        assert!(lines[8].trim_start().starts_with('0'));
+    }
+
+    #[test]
+    fn colors_test() {
+        let braille = Braille::new(20, 10);
+        assert_eq!(braille.escape(Color::from("#ff8000")), "\x1b[38;2;255;128;0m");
+        let braille = braille.colors(AnsiColors::Palette256);
+        assert_eq!(braille.escape(Color::from("#ff8000")), "\x1b[38;5;214m");
+        let text = braille.render(&chart(), vec![serie()].into_iter()).unwrap();
+        assert!(text.contains("\x1b[38;5;196m"));
+    }
+
+    #[test]
+    fn not_enough_space_test() {
+        let result = Braille::new(20, 3).render(&chart(), vec![serie()].into_iter());
+        assert_eq!(result.err().unwrap().to_string(),
+                   "There are not enough width and height to form graph with axis.");
+    }
+
+    #[test]
+    fn x_labels_fit_line_test() {
+        let labels = vec!["0".to_string(), "12345".to_string(), "2".to_string()];
+        assert_eq!(x_labels(&labels, &[0, 1, 3], 0, 4), "0  2");
+        assert_eq!(x_labels(&labels[1..2], &[2], 0, 5), "12345");
+    }
+}
@@ -0,0 +1,216 @@
+use std::io::{self, Write};
+
+use BitMap;
+use bitmap::BmpEncoder;
+use Color;
+use DisplayPoint;
+use layout::Rect;
+
+const MAX_COLORS: usize = 256;
+
+
+/// Palette-indexed pixel surface charts are drawn on.
+///
+/// Pixels are stored row by row starting from the bottom one, the same
+/// order a bmp file keeps them in.
+#[derive(Debug, Clone)]
+pub struct Canvas {
+    width: usize,
+    height: usize,
+    palette: Vec<Color>,
+    pixs: Vec<u8>,
+}
+
+impl Canvas {
+    /// Canvas filled with `background_color`.
+    pub fn new<C: Into<Color>>(width: usize, height: usize, background_color: C) -> Canvas {
+        let mut canvas = Canvas {
+            width,
+            height,
+            palette: vec![],
+            pixs: vec![],
+        };
+        let background = canvas.add_color(background_color);
+        canvas.pixs = vec![background; width * height];
+        canvas
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn area(&self) -> Rect {
+        Rect::new(0, 0, self.width, self.height)
+    }
+
+    /// Palette index of `color`, added to the palette if it is not there yet.
+    /// Once the palette has 256 colors the nearest one is used instead.
+    pub fn add_color<C: Into<Color>>(&mut self, color: C) -> u8 {
+        let color = color.into();
+        match self.palette.iter().position(|c| *c == color) {
+            Some(i) => i as u8,
+            None if self.palette.len() == MAX_COLORS => nearest(&self.palette, color),
+            None => {
+                self.palette.push(color);
+                (self.palette.len() - 1) as u8
+            }
+        }
+    }
+
+    pub fn palette(&self) -> &[Color] {
+        &self.palette
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixs
+    }
+
+    pub fn fill(&mut self, rect: &Rect, color: u8) {
+        if rect.x >= self.width {
+            return;
+        }
+        let right = if rect.right() < self.width {
+            rect.right()
+        } else {
+            self.width
+        };
+        for y in rect.y..rect.top() {
+            if y >= self.height {
+                break;
+            }
+            let row = y * self.width;
+            for p in &mut self.pixs[(row + rect.x)..(row + right)] {
+                *p = color;
+            }
+        }
+    }
+
+    /// Sets every point lying on the canvas to `color`, others are skipped.
+    pub fn draw_pixels<I>(&mut self, points: I, color: u8)
+        where I: IntoIterator<Item = DisplayPoint>
+    {
+        for p in points {
+            if p.x < self.width && p.y < self.height {
+                let i = p.y * self.width + p.x;
+                self.pixs[i] = color;
+            }
+        }
+    }
+
+    /// Writes the canvas as a bmp file, one row at a time.
+    pub fn write_to<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut encoder = BmpEncoder::new(writer, self.width, self.height, &self.palette)?;
+        if self.width > 0 {
+            for row in self.pixs.chunks(self.width) {
+                encoder.write_row(row)?;
+            }
+        }
+        encoder.finish().map(|_| ())
+    }
+
+    /// Canvas encoded as a bmp file.
+    pub fn as_vec(&self) -> Vec<u8> {
+        let mut picture = vec![];
+        self.write_to(&mut picture).expect("writing to a Vec can't fail");
+        picture
+    }
+}
+
+/// Canvas showing a picture read by `BitMap::read`, to draw charts over.
+impl From<&BitMap> for Canvas {
+    fn from(bitmap: &BitMap) -> Canvas {
+        let mut pixs = bitmap.pixels().to_vec();
+        pixs.resize(bitmap.width() * bitmap.height(), 0);
+        let mut palette = bitmap.palette().to_vec();
+        palette.truncate(MAX_COLORS);
+        if palette.is_empty() {
+            palette.push(Color::from("#000000"));
+        }
+        Canvas {
+            width: bitmap.width(),
+            height: bitmap.height(),
+            palette,
+            pixs,
+        }
+    }
+}
+
+impl From<&Canvas> for BitMap {
+    fn from(canvas: &Canvas) -> BitMap {
+        let mut bitmap = BitMap::new(canvas.width, canvas.height);
+        for color in &canvas.palette {
+            bitmap.add_color(*color);
+        }
+        bitmap.add_pixels(&canvas.pixs);
+        bitmap
+    }
+}
+
+fn nearest(palette: &[Color], color: Color) -> u8 {
+    let distance = |c: &Color| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(c.r, color.r) + d(c.g, color.g) + d(c.b, color.b)
+    };
+    let (i, _) = palette.iter()
+        .enumerate()
+        .min_by_key(|&(_, c)| distance(c))
+        .expect("palette of a canvas is never empty");
+    i as u8
+}
+
+
+#[cfg(test)]
+mod tests {
+    use canvas::Canvas;
+    use layout::Rect;
+    use BitMap;
+    use Color;
+    use DisplayPoint;
+
+    #[test]
+    fn same_color_is_added_once_test() {
+        let mut canvas = Canvas::new(10, 10, "#ffffff");
+        let red = canvas.add_color("#ff0000");
+        assert_eq!(canvas.add_color("#FF0000"), red);
+        assert_eq!(canvas.add_color("#ffffff"), 0);
+        assert_eq!(canvas.palette().len(), 2);
+    }
+
+    #[test]
+    fn drawing_is_limited_to_canvas_test() {
+        let mut canvas = Canvas::new(4, 3, "#ffffff");
+        let red = canvas.add_color("#ff0000");
+        canvas.fill(&Rect::new(2, 1, 5, 5), red);
+        canvas.draw_pixels(vec![DisplayPoint { x: 0, y: 0 }, DisplayPoint { x: 9, y: 9 }], red);
+        assert_eq!(canvas.pixels(),
+                   &[1, 0, 0, 0, 0, 0, 1, 1, 0, 0, 1, 1][..]);
+
+        // nothing of a rectangle right of the canvas is filled
+        canvas.fill(&Rect::new(6, 0, 2, 2), red);
+        assert_eq!(canvas.pixels(),
+                   &[1, 0, 0, 0, 0, 0, 1, 1, 0, 0, 1, 1][..]);
+    }
+
+    #[test]
+    fn canvas_from_bitmap_test() {
+        let mut bitmap = BitMap::new(2, 1);
+        bitmap.add_color("#000000");
+        bitmap.add_color("#ffffff");
+        bitmap.add_pixels(&[1, 0]);
+        let canvas = Canvas::from(&bitmap);
+        assert_eq!(canvas.pixels(), &[1, 0][..]);
+
+        let mut canvas = Canvas::new(1, 1, "#000000");
+        for i in 1..256 {
+            canvas.add_color(Color { r: i as u8, g: 0, b: 0 });
+        }
+        // no room for another color, the nearest one stands in for it
+        assert_eq!(canvas.add_color("#f00101"), 0xf0);
+        assert_eq!(canvas.palette().len(), 256);
+    }
+
+}
@@ -1,17 +1,16 @@
 use std::f64;
+use std::io::{self, Write};
+use std::vec;
 
-use BitMap;
 use line;
 use Axis;
+use Canvas;
+use Color;
+use font;
+use layout::{Layout, Rect, Texts, MIN_SIZE};
+use polar::PolarPlot;
 
-const W_ARROW: usize = 4;      //width of arrow
-const W_NUMBER: usize = 4;     //number width in pixel
-const H_NUMBER: usize = 5;     //number height in pixels
-const W_BORDER: usize = 1;     //space around graph width
-const H_ARROW_HALF: usize = 3;
-
-const LEFT_SHIFT: usize = W_BORDER + W_NUMBER + H_NUMBER;
-const RIGHT_SHIFT: usize = W_ARROW;
+const LAYOUT_PASSES: usize = 2;   // axis labels depend on plot size and back
 
 
 quick_error! {
@@ -19,18 +18,62 @@ quick_error! {
     pub enum GraphError {
         NotEnoughPoints {
             description("There are not enough points to display on graph.")
+            display("There are not enough points to display on graph.")
         }
         NotEnoughSpace {
             description("There are not enough width and height to form graph with axis.")
+            display("There are not enough width and height to form graph with axis.")
         }
         NonUniquePoints {
             description("There are only one unique point. Can't construct line.")
+            display("There are only one unique point. Can't construct line.")
+        }
+        NoFiniteSegment {
+            description("There are no two finite points in a row. Can't construct line.")
+            display("There are no two finite points in a row. Can't construct line.")
+        }
+        CellOutOfGrid {
+            description("Chart cell lies outside of the figure grid.")
+            display("Chart cell lies outside of the figure grid.")
+        }
+        InvalidExpression(position: usize, message: String) {
+            description("Expression can't be parsed.")
+            display("Invalid expression at position {}: {}", position, message)
+        }
+        Io(err: io::Error) {
+            from()
+            description("Data can't be read.")
+            display("Data can't be read: {}", err)
+            cause(err)
+        }
+        MalformedRow(line: usize, message: String) {
+            description("Row of data can't be parsed.")
+            display("Malformed row at line {}: {}", line, message)
+        }
+        UnknownColumn(name: String) {
+            description("There is no such column in data.")
+            display("There is no column '{}' in data.", name)
+        }
+        InvalidColor(color: String) {
+            description("Color is not valid.")
+            display("'{}' is not a color like #rrggbb.", color)
+        }
+        InvalidSpec(message: String) {
+            description("Chart specification is not valid.")
+            display("Invalid chart specification: {}", message)
+        }
+        InvalidBitmap(message: String) {
+            description("Bitmap can't be read.")
+            display("Bitmap can't be read: {}", message)
         }
     }
 }
 
 pub type GraphResult = Result<Vec<u8>, GraphError>;
 
+/// Colors given in turn to series whose color is not chosen.
+pub const PALETTE: [&str; 6] = ["#1f77b4", "#d62728", "#2ca02c", "#ff7f0e", "#9467bd", "#8c564b"];
+
 
 pub trait InPoint: Into<Point> + PartialEq {}
 impl<T: Into<Point> + PartialEq> InPoint for T {}
@@ -42,7 +85,7 @@ impl<T, P> IterInPoint<P> for T
 {
 }
 
-#[derive(Clone, Copy)]
+#[derive(PartialEq, Clone, Copy, Debug)]
 pub struct Point {
     pub x: f64,
     pub y: f64,
@@ -60,6 +103,27 @@ impl From<(f64, f64)> for Point {
     }
 }
 
+/// A missing y value is a gap in the line, like NaN.
+impl<'a> From<&'a (f64, Option<f64>)> for Point {
+    fn from(t: &'a (f64, Option<f64>)) -> Point {
+        Point { x: t.0, y: t.1.unwrap_or(f64::NAN) }
+    }
+}
+
+impl From<(f64, Option<f64>)> for Point {
+    fn from(t: (f64, Option<f64>)) -> Point {
+        Point::from(&t)
+    }
+}
+
+impl Point {
+    /// Whether both coordinates are finite. Other points are gaps: lines
+    /// of a serie break there.
+    pub fn is_finite(&self) -> bool {
+        self.x.is_finite() && self.y.is_finite()
+    }
+}
+
 #[derive(PartialEq, Clone, Copy, Debug)]
 pub struct DisplayPoint {
     pub x: usize,
@@ -71,6 +135,7 @@ pub struct DisplayPoint {
 pub struct Serie<T: IterInPoint<P, Item = P>, P: InPoint> {
     pub iter: T,
     color: String,
+    name: Option<String>,
     max_x: f64,
     max_y: f64,
     min_x: f64,
@@ -81,34 +146,80 @@ impl<P: InPoint, T: IterInPoint<P>> Serie<T, P> {
     pub fn new<S: Into<String>>(iter: T, color: S) -> Result<Self, GraphError> {
 
         let color = color.into();
+        color.parse::<Color>()?;
 
         if iter.clone().nth(1).is_none() {
             return Err(GraphError::NotEnoughPoints);
         }
 
-        let first = iter.clone().nth(0).unwrap();
-        if !iter.clone().skip(1).any(move |p| p != first) {
+        let points = iter.clone().map(Into::into);
+        let finite_segment = |(a, b): (Point, Point)| a.is_finite() && b.is_finite();
+        if !points.clone().zip(points.clone().skip(1)).any(finite_segment) {
+            return Err(GraphError::NoFiniteSegment);
+        }
+
+        let mut finite = points.filter(Point::is_finite);
+        let first = finite.next().unwrap();
+        if !finite.any(move |p| p != first) {
             return Err(GraphError::NonUniquePoints);
         }
 
         let (max_x, min_x, max_y, min_y) = Self::calculate_max_min(iter.clone());
 
         Ok(Serie {
-            iter: iter,
-            color: color,
-            max_x: max_x,
-            max_y: max_y,
-            min_x: min_x,
-            min_y: min_y,
+            iter,
+            color,
+            name: None,
+            max_x,
+            max_y,
+            min_x,
+            min_y,
+        })
+    }
+
+    /// Same serie with its points collected into a vector.
+    pub fn collect_points(&self) -> Serie<vec::IntoIter<Point>, Point> {
+        Serie {
+            iter: self.iter.clone().map(Into::into).collect::<Vec<Point>>().into_iter(),
+            color: self.color.clone(),
+            name: self.name.clone(),
+            max_x: self.max_x,
+            max_y: self.max_y,
+            min_x: self.min_x,
+            min_y: self.min_y,
+        }
+    }
+
+    /// Smallest and largest coordinates of the serie points, gaps left out.
+    pub fn bounds(&self) -> (Point, Point) {
+        (Point {
+            x: self.min_x,
+            y: self.min_y,
+        },
+         Point {
+            x: self.max_x,
+            y: self.max_y,
         })
     }
 
+    pub fn color(&self) -> &str {
+        &self.color
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Name of the serie shown in the chart legend.
+    pub fn add_name<S: Into<String>>(self, name: S) -> Self {
+        Serie { name: Some(name.into()), ..self }
+    }
+
     fn calculate_max_min(iter: T) -> (f64, f64, f64, f64) {
         let (mut min_x, mut max_x) = (f64::INFINITY, f64::NEG_INFINITY);
         let (mut min_y, mut max_y) = (f64::INFINITY, f64::NEG_INFINITY);
 
-        for p in iter {
-            let p = p.into();
+        for p in iter.map(Into::into).filter(Point::is_finite) {
             if p.x > max_x {
                 max_x = p.x;
             }
@@ -127,16 +238,33 @@ impl<P: InPoint, T: IterInPoint<P>> Serie<T, P> {
 }
 
 
-#[derive(Debug)]
+/// Serie with its points collected, as `Serie::collect_points` gives it.
+pub type Points = Serie<vec::IntoIter<Point>, Point>;
+
+
+/// Coordinate system the points of series are drawn in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Coordinates {
+    /// Points are `(x, y)`, axes lie along the bottom and the left of the plot.
+    Cartesian,
+    /// Points are `(theta, r)`, `theta` being in radians, drawn on a
+    /// circular grid. The y axis gives the radius range.
+    Polar,
+}
+
+
+#[derive(Debug, Clone)]
 pub struct Chart {
     width: usize,
     height: usize,
-    background_color: u8,
-    axis_color: u8,
-    pixs: Vec<u8>,
-    picture: BitMap,
+    background_color: Color,
+    axis_color: Color,
     axis_x: Option<Axis>,
     axis_y: Option<Axis>,
+    title: Option<String>,
+    axis_x_title: Option<String>,
+    axis_y_title: Option<String>,
+    coordinates: Coordinates,
 }
 
 impl Chart {
@@ -146,78 +274,110 @@ impl Chart {
                axis_color: &str)
                -> Result<Self, GraphError> {
 
-        if width < (2 * H_NUMBER + 2 * W_NUMBER + W_ARROW + 2 * W_BORDER) ||
-           height < (2 * H_NUMBER + 2 * W_NUMBER + W_ARROW + 2 * W_BORDER) {
+        if width < MIN_SIZE || height < MIN_SIZE {
             return Err(GraphError::NotEnoughSpace);
         };
 
-        let mut picture = BitMap::new(width, height);
-
-        let background_color_number = picture.add_color(background_color);
-
-        let axis_color_number = picture.add_color(axis_color);
-
-        let size = width * height;
-
-        let pixs = vec![background_color_number;  size];
-
         Ok(Chart {
-            width: width,
-            height: height,
-            background_color: background_color_number,
-            axis_color: axis_color_number,
-            pixs: pixs,
-            picture: picture,
+            width,
+            height,
+            background_color: background_color.parse()?,
+            axis_color: axis_color.parse()?,
             axis_x: None,
             axis_y: None,
+            title: None,
+            axis_x_title: None,
+            axis_y_title: None,
+            coordinates: Coordinates::Cartesian,
         })
     }
 
     pub fn add_axis_x(self, axis_x: Axis) -> Chart {
-        let new_axis_x = Some(Axis::set_axis_manual(axis_x.min_value,
-                                                    axis_x.max_value,
-                                                    axis_x.interval_count,
-                                                    axis_x.decimal_places,
-                                                    self.width));
-        Chart { axis_x: new_axis_x, ..self }
+        Chart { axis_x: Some(axis_x), ..self }
     }
 
 
     pub fn add_axis_y(self, axis_y: Axis) -> Chart {
-        let new_axis_y = Some(Axis::set_axis_manual(axis_y.min_value,
-                                                    axis_y.max_value,
-                                                    axis_y.interval_count,
-                                                    axis_y.decimal_places,
-                                                    self.height)
-            .rotate());
-        Chart { axis_y: new_axis_y, ..self }
+        Chart { axis_y: Some(axis_y.rotate()), ..self }
+    }
+
+    pub fn add_title<S: Into<String>>(self, title: S) -> Chart {
+        Chart { title: Some(title.into()), ..self }
     }
 
-    fn draw_serie<P: InPoint, T: IterInPoint<P>>(&mut self, serie: Serie<T, P>) {
+    pub fn add_axis_x_title<S: Into<String>>(self, title: S) -> Chart {
+        Chart { axis_x_title: Some(title.into()), ..self }
+    }
 
-        let func_points = {
+    pub fn add_axis_y_title<S: Into<String>>(self, title: S) -> Chart {
+        Chart { axis_y_title: Some(title.into()), ..self }
+    }
 
-            let max_width = self.width - RIGHT_SHIFT;
+    /// Draws series in polar coordinates, see `Coordinates::Polar`.
+    pub fn polar(self) -> Chart {
+        Chart { coordinates: Coordinates::Polar, ..self }
+    }
 
-            let max_height = self.height - RIGHT_SHIFT;
+    pub fn coordinates(&self) -> Coordinates {
+        self.coordinates
+    }
 
-            let function = self.serie_to_points(&serie);
+    pub fn width(&self) -> usize {
+        self.width
+    }
 
-            line::extrapolate(function)
-                .filter(|p| {
-                    p.x >= LEFT_SHIFT && p.x <= max_width && p.y >= LEFT_SHIFT && p.y <= max_height
-                })
-                .collect::<Vec<DisplayPoint>>()
+    pub fn height(&self) -> usize {
+        self.height
+    }
 
-        };
+    pub fn background_color(&self) -> Color {
+        self.background_color
+    }
+
+    pub fn axis_color(&self) -> Color {
+        self.axis_color
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    pub fn axis_x_title(&self) -> Option<&str> {
+        self.axis_x_title.as_deref()
+    }
+
+    pub fn axis_y_title(&self) -> Option<&str> {
+        self.axis_y_title.as_deref()
+    }
+
+    /// Axis x set with `add_axis_x`, `None` when it is calculated from series.
+    pub fn axis_x(&self) -> Option<&Axis> {
+        self.axis_x.as_ref()
+    }
+
+    /// Axis y set with `add_axis_y`, `None` when it is calculated from series.
+    pub fn axis_y(&self) -> Option<&Axis> {
+        self.axis_y.as_ref()
+    }
+
+    fn draw_serie<P: InPoint, T: IterInPoint<P>>(canvas: &mut Canvas,
+                                                  serie: &Serie<T, P>,
+                                                  plot: &Rect,
+                                                  axis_x: &Axis,
+                                                  axis_y: &Axis,
+                                                  color: u8) {
 
-        let points_color_number = self.picture.add_color(&*serie.color);
+        let positions = serie.iter.clone().map(|p| Self::plot_offset(p.into(), plot, axis_x, axis_y));
+        let func_points = line::clip_polyline(positions, plot.width, plot.height)
+            .into_iter()
+            .map(|p| DisplayPoint { x: plot.x + p.x, y: plot.y + p.y })
+            .collect::<Vec<DisplayPoint>>();
 
-        self.draw_pixels(func_points, points_color_number);
+        canvas.draw_pixels(func_points, color);
     }
 
 
-    fn calc_axis<S, T, P>(&mut self, series: S)
+    fn calc_bounds<S, T, P>(series: S) -> (f64, f64, f64, f64)
         where S: Iterator<Item = Serie<T, P>>,
               T: IterInPoint<P>,
               P: InPoint
@@ -241,134 +401,430 @@ impl Chart {
             }
         }
 
-        if self.axis_x.is_none() {
-            self.axis_x = Some(Axis::set_axis_auto(max_x, min_x, self.width));
+        (max_x, min_x, max_y, min_y)
+    }
+
+    /// Axes `draw` would place along a plot `width` x `height` pixels large,
+    /// those set with `add_axis_x` and `add_axis_y` or calculated from series.
+    pub fn fit_axes<S, T, P>(&self, series: S, width: usize, height: usize) -> (Axis, Axis)
+        where S: Iterator<Item = Serie<T, P>>,
+              T: IterInPoint<P>,
+              P: InPoint
+    {
+        let (max_x, min_x, max_y, min_y) = if self.axis_x.is_none() || self.axis_y.is_none() {
+            Self::calc_bounds(series)
+        } else {
+            (0f64, 0f64, 0f64, 0f64)
+        };
+
+        let axis_x = match self.axis_x {
+            Some(ref axis) => axis.fit(width),
+            None => Axis::set_axis_auto(max_x, min_x, width),
+        };
+        let axis_y = match self.axis_y {
+            Some(ref axis) => axis.fit(height),
+            None => Axis::set_axis_auto(max_y, min_y, height).rotate(),
+        };
+        (axis_x, axis_y)
+    }
+
+    /// Axes of `series` already converted with `to_cartesian` when the chart
+    /// is polar, fitted to a plot `width` x `height` pixels large.
+    pub fn fit_cartesian_axes(&self, series: &[Points], width: usize, height: usize) -> (Axis, Axis) {
+        if self.coordinates == Coordinates::Polar {
+            let (mut min, mut max) = (Point { x: f64::INFINITY, y: f64::INFINITY },
+                                      Point { x: f64::NEG_INFINITY, y: f64::NEG_INFINITY });
+            for (s_min, s_max) in series.iter().map(|s| s.bounds()) {
+                min = Point { x: min.x.min(s_min.x), y: min.y.min(s_min.y) };
+                max = Point { x: max.x.max(s_max.x), y: max.y.max(s_max.y) };
+            }
+            (Axis::set_axis_auto(max.x, min.x, width), Axis::set_axis_auto(max.y, min.y, height))
+        } else {
+            self.fit_axes(series.iter().cloned(), width, height)
         }
+    }
 
-        if self.axis_y.is_none() {
-            self.axis_y = Some(Axis::set_axis_auto(max_y, min_y, self.height).rotate());
+    /// Pixel of the picture `draw` gives that `point` of the data lands on,
+    /// `None` when it lies out of the plot. Axes are resolved as `draw` does,
+    /// from `series` unless they are set, so pass the same series.
+    pub fn data_to_pixel<S, T, P>(&self, series: S, point: Point) -> Option<DisplayPoint>
+        where S: Iterator<Item = Serie<T, P>> + Clone,
+              T: IterInPoint<P>,
+              P: InPoint
+    {
+        match self.resolve(series) {
+            Resolved::Cartesian(plot, axis_x, axis_y) => {
+                let (x, y) = Self::plot_offset(point, &plot, &axis_x, &axis_y);
+                let (x, y) = (x.round(), y.round());
+                if (0f64..=plot.width as f64).contains(&x) && (0f64..=plot.height as f64).contains(&y) {
+                    Some(DisplayPoint { x: plot.x + x as usize, y: plot.y + y as usize })
+                } else {
+                    None
+                }
+            }
+            Resolved::Polar(plot) => plot.to_display(point),
         }
     }
 
-    pub fn draw<S, T, P>(&mut self, series: S) -> Vec<u8>
+    /// Data shown at `pixel` of the picture `draw` gives, `None` out of the
+    /// plot; the inverse of `data_to_pixel` up to the pixel size.
+    pub fn pixel_to_data<S, T, P>(&self, series: S, pixel: DisplayPoint) -> Option<Point>
         where S: Iterator<Item = Serie<T, P>> + Clone,
               T: IterInPoint<P>,
               P: InPoint
     {
+        match self.resolve(series) {
+            Resolved::Cartesian(plot, axis_x, axis_y) => {
+                if !plot.contains(pixel) {
+                    return None;
+                }
+                let resolution_x = (axis_x.max_value - axis_x.min_value) / (plot.width as f64);
+                let resolution_y = (axis_y.max_value - axis_y.min_value) / (plot.height as f64);
+                Some(Point {
+                    x: axis_x.min_value + ((pixel.x - plot.x) as f64) * resolution_x,
+                    y: axis_y.min_value + ((pixel.y - plot.y) as f64) * resolution_y,
+                })
+            }
+            Resolved::Polar(plot) => plot.to_data(pixel),
+        }
+    }
 
-        if self.axis_x.is_none() || self.axis_y.is_none() {
-            self.calc_axis(series.clone());
+    // plot and axes `draw` places series with on a canvas of the chart size
+    fn resolve<S, T, P>(&self, series: S) -> Resolved
+        where S: Iterator<Item = Serie<T, P>> + Clone,
+              T: IterInPoint<P>,
+              P: InPoint
+    {
+        let area = Rect::new(0, 0, self.width, self.height);
+        let legend = series.clone().filter_map(|s| s.name).collect::<Vec<String>>();
+        if self.coordinates == Coordinates::Polar {
+            let (_, plot) = self.calc_polar_layout(area, series, &legend);
+            Resolved::Polar(plot)
+        } else {
+            let (layout, axis_x, axis_y) = self.calc_layout(area, series, &legend);
+            Resolved::Cartesian(layout.plot, axis_x, axis_y)
         }
+    }
 
-        self.draw_axis();
+    /// Measures every text of the chart and fits the axes to the plot left
+    /// between them, so that neither labels nor titles overlap the plot.
+    fn calc_layout<S, T, P>(&self, area: Rect, series: S, legend: &[String]) -> (Layout, Axis, Axis)
+        where S: Iterator<Item = Serie<T, P>> + Clone,
+              T: IterInPoint<P>,
+              P: InPoint
+    {
+        self.layout_with(area, legend, |width, height| self.fit_axes(series.clone(), width, height))
+    }
 
-        for serie in series {
-            self.draw_serie(serie);
+    /// Where titles, tick labels, the `legend` and the plot go inside `area`,
+    /// with the axes `fit` gives for a plot of the width and height it is
+    /// called with. `fit_axes` is what the chart itself uses.
+    pub fn layout_with<F>(&self, area: Rect, legend: &[String], fit: F) -> (Layout, Axis, Axis)
+        where F: Fn(usize, usize) -> (Axis, Axis)
+    {
+        let mut plot = area;
+        let mut layout = None;
+        for _ in 0..LAYOUT_PASSES {
+            let (axis_x, axis_y) = fit(plot.width, plot.height);
+            let ticks_x = axis_x.labels();
+            let ticks_y = axis_y.labels();
+            let texts = Texts {
+                title: self.title.as_deref(),
+                axis_x_title: self.axis_x_title.as_deref(),
+                axis_y_title: self.axis_y_title.as_deref(),
+                ticks_x: &ticks_x,
+                ticks_y: &ticks_y,
+                legend,
+            };
+            let new_layout = Layout::new(area, &texts);
+            plot = new_layout.plot;
+            layout = Some(new_layout);
         }
 
-        self.picture.add_pixels(&self.pixs);
-
-        self.picture.as_vec()
+        let (axis_x, axis_y) = fit(plot.width, plot.height);
+        (layout.unwrap(), axis_x, axis_y)
     }
 
-    fn draw_axis(&mut self) {
+    /// Bmp file of the chart. Every call draws a fresh picture, the chart
+    /// itself is left as it was, so one chart serves any number of drawings.
+    pub fn draw<S, T, P>(&self, series: S) -> Vec<u8>
+        where S: Iterator<Item = Serie<T, P>> + Clone,
+              T: IterInPoint<P>,
+              P: InPoint
+    {
+        self.render(series).as_vec()
+    }
 
-        let axis_x = self.axis_x.clone().unwrap();
+    /// Draws the chart and streams it to `writer` as a bmp file, so no
+    /// encoded copy of the picture is kept in memory.
+    pub fn write_to<S, T, P, W>(&self, series: S, writer: W) -> io::Result<()>
+        where S: Iterator<Item = Serie<T, P>> + Clone,
+              T: IterInPoint<P>,
+              P: InPoint,
+              W: Write
+    {
+        self.render(series).write_to(writer)
+    }
 
-        let axis_y = self.axis_y.clone().unwrap();
+    /// Canvas of the chart size with the chart drawn on it, to be encoded
+    /// in any format.
+    pub fn render<S, T, P>(&self, series: S) -> Canvas
+        where S: Iterator<Item = Serie<T, P>> + Clone,
+              T: IterInPoint<P>,
+              P: InPoint
+    {
+        let mut canvas = Canvas::new(self.width, self.height, self.background_color);
 
-        let minor_net = self.get_minor_net(&axis_x, &axis_y);
+        let area = canvas.area();
 
-        let axis_color = self.axis_color;
+        self.draw_on(&mut canvas, area, series);
 
-        self.draw_pixels(axis_x.create_points(), axis_color);
+        canvas
+    }
 
-        self.draw_pixels(axis_y.create_points(), axis_color);
+    /// Draws the chart inside `area` of `canvas`, whatever the chart size is.
+    pub fn draw_on<S, T, P>(&self, canvas: &mut Canvas, area: Rect, series: S)
+        where S: Iterator<Item = Serie<T, P>> + Clone,
+              T: IterInPoint<P>,
+              P: InPoint
+    {
+        let background_color = canvas.add_color(self.background_color);
+        self.draw_layers(canvas, area, series, Some(background_color));
+    }
 
-        self.draw_pixels(minor_net, axis_color);
+    /// Draws the chart in `area` of `canvas` over the picture already there,
+    /// leaving out the background of the chart.
+    pub fn draw_over<S, T, P>(&self, canvas: &mut Canvas, area: Rect, series: S)
+        where S: Iterator<Item = Serie<T, P>> + Clone,
+              T: IterInPoint<P>,
+              P: InPoint
+    {
+        self.draw_layers(canvas, area, series, None);
     }
 
-    fn get_minor_net(&self, axis_x: &Axis, axis_y: &Axis) -> Vec<DisplayPoint> {
-        let mut v: Vec<DisplayPoint> = vec![];
-        for i in 0..axis_x.interval_count {
-            let shift = LEFT_SHIFT + ((axis_x.scale_interval_pix * (i as f64)).round() as usize);
-            for j in LEFT_SHIFT..(self.height - H_ARROW_HALF) {
-                if j % 2 != 0 {
-                    v.push(DisplayPoint { x: shift, y: j });
-                }
+    fn draw_layers<S, T, P>(&self,
+                            canvas: &mut Canvas,
+                            area: Rect,
+                            series: S,
+                            background_color: Option<u8>)
+        where S: Iterator<Item = Serie<T, P>> + Clone,
+              T: IterInPoint<P>,
+              P: InPoint
+    {
+        let axis_color = canvas.add_color(self.axis_color);
+
+        let legend = series.clone().filter_map(|s| s.name).collect::<Vec<String>>();
+
+        if self.coordinates == Coordinates::Polar {
+            return self.draw_polar_on(canvas, area, series, &legend, background_color, axis_color);
+        }
+
+        let (layout, axis_x, axis_y) = self.calc_layout(area, series.clone(), &legend);
+
+        if let Some(background_color) = background_color {
+            canvas.fill(&layout.area, background_color);
+        }
+
+        self.draw_texts(canvas, &layout, axis_color);
+
+        Self::draw_axis(canvas, &layout.plot, &axis_x, &axis_y, axis_color);
+
+        let mut legend_entry = 0;
+        for serie in series {
+            let color = canvas.add_color(&*serie.color);
+            Self::draw_serie(canvas, &serie, &layout.plot, &axis_x, &axis_y, color);
+            if let Some(ref name) = serie.name {
+                Self::draw_legend_entry(canvas, &layout, legend_entry, name, color, axis_color);
+                legend_entry += 1;
             }
         }
+    }
 
-        for i in 0..axis_y.interval_count {
-            let shift = LEFT_SHIFT + ((axis_y.scale_interval_pix * (i as f64)).round() as usize);
-            for j in LEFT_SHIFT..(self.width - H_ARROW_HALF) {
-                if j % 2 != 0 {
-                    v.push(DisplayPoint { x: j, y: shift });
-                }
+    fn draw_polar_on<S, T, P>(&self,
+                              canvas: &mut Canvas,
+                              area: Rect,
+                              series: S,
+                              legend: &[String],
+                              background_color: Option<u8>,
+                              axis_color: u8)
+        where S: Iterator<Item = Serie<T, P>> + Clone,
+              T: IterInPoint<P>,
+              P: InPoint
+    {
+        let (layout, plot) = self.calc_polar_layout(area, series.clone(), legend);
+
+        if let Some(background_color) = background_color {
+            canvas.fill(&layout.area, background_color);
+        }
+
+        self.draw_texts(canvas, &layout, axis_color);
+
+        canvas.draw_pixels(plot.create_points(), axis_color);
+
+        let mut legend_entry = 0;
+        for serie in series {
+            let color = canvas.add_color(&*serie.color);
+            for part in finite_parts(serie.iter.clone().map(Into::into)) {
+                let points = plot.polyline(part.into_iter());
+                canvas.draw_pixels(points.into_iter().filter(|p| plot.contains(*p)), color);
+            }
+            if let Some(ref name) = serie.name {
+                Self::draw_legend_entry(canvas, &layout, legend_entry, name, color, axis_color);
+                legend_entry += 1;
             }
         }
-        v
     }
 
-    fn serie_to_points<'b, P: InPoint, T: IterInPoint<P>>
-        (&'b mut self,
-         serie: &'b Serie<T, P>)
-         -> Box<Iterator<Item = DisplayPoint> + 'b> {
+    /// Layout of a polar chart, its radius range is taken from the y axis.
+    fn calc_polar_layout<S, T, P>(&self,
+                                  area: Rect,
+                                  series: S,
+                                  legend: &[String])
+                                  -> (Layout, PolarPlot)
+        where S: Iterator<Item = Serie<T, P>>,
+              T: IterInPoint<P>,
+              P: InPoint
+    {
+        let texts = Texts {
+            title: self.title.as_deref(),
+            axis_x_title: self.axis_x_title.as_deref(),
+            axis_y_title: self.axis_y_title.as_deref(),
+            ticks_x: &[],
+            ticks_y: &[],
+            legend,
+        };
+        let layout = Layout::new(area, &texts);
+        let radius = PolarPlot::radius_in(&layout.plot);
+
+        let axis_r = match self.axis_y {
+            Some(ref axis) => axis.fit(radius),
+            None => {
+                let (_, _, max_r, min_r) = Self::calc_bounds(series);
+                let min_r = if min_r > 0f64 { 0f64 } else { min_r };
+                Axis::set_axis_auto(max_r, min_r, radius)
+            }
+        };
 
-        let width_available = self.width - LEFT_SHIFT - RIGHT_SHIFT;
+        let plot = PolarPlot::new(&layout.plot, axis_r);
+        (layout, plot)
+    }
 
-        let height_available = self.height - LEFT_SHIFT - RIGHT_SHIFT;
+    fn draw_axis(canvas: &mut Canvas, plot: &Rect, axis_x: &Axis, axis_y: &Axis, axis_color: u8) {
 
-        let axis_x = self.axis_x.clone().unwrap();
+        let minor_net = Self::get_minor_net(plot, axis_x, axis_y);
 
-        let axis_y = self.axis_y.clone().unwrap();
+        canvas.draw_pixels(axis_x.create_points(plot), axis_color);
 
-        let resolution_x: f64 = (axis_x.max_value - axis_x.min_value) / (width_available as f64);
-        let resolution_y: f64 = (axis_y.max_value - axis_y.min_value) / (height_available as f64);
+        canvas.draw_pixels(axis_y.create_points(plot), axis_color);
 
-        let serie_iter = serie.iter.clone();
+        canvas.draw_pixels(minor_net, axis_color);
+    }
 
-        Box::new(serie_iter.map(move |p| {
-            let p = p.into();
-            let id_x = ((p.x - axis_x.min_value) / resolution_x).round();
-            let id_y = ((p.y - axis_y.min_value) / resolution_y).round();
+    fn draw_texts(&self, canvas: &mut Canvas, layout: &Layout, axis_color: u8) {
+        let mut v: Vec<DisplayPoint> = vec![];
+        if let (Some(rect), Some(text)) = (layout.title, self.title.as_ref()) {
+            v.extend(font::create_text(text, rect.x, rect.y));
+        }
+        if let (Some(rect), Some(text)) = (layout.axis_x_title, self.axis_x_title.as_ref()) {
+            v.extend(font::create_text(text, rect.x, rect.y));
+        }
+        if let (Some(rect), Some(text)) = (layout.axis_y_title, self.axis_y_title.as_ref()) {
+            v.extend(font::create_text_vertical(text, rect.x, rect.y));
+        }
+        canvas.draw_pixels(v, axis_color);
+    }
 
-            let id_x = if id_x < 0f64 {
-                LEFT_SHIFT - 1
-            } else if id_x > (width_available as f64) {
-                width_available + LEFT_SHIFT + 1
-            } else {
-                (id_x as usize) + LEFT_SHIFT
-            };
+    fn draw_legend_entry(canvas: &mut Canvas,
+                         layout: &Layout,
+                         i: usize,
+                         name: &str,
+                         color: u8,
+                         axis_color: u8) {
+        if let Some((swatch, text)) = layout.legend_entry(i) {
+            let sample = (swatch.x..swatch.right()).map(|x| DisplayPoint { x, y: swatch.y });
+            canvas.draw_pixels(sample, color);
+            canvas.draw_pixels(font::create_text(name, text.x, text.y), axis_color);
+        }
+    }
 
-            let id_y = if id_y < 0f64 {
-                LEFT_SHIFT - 1
-            } else if id_y > (height_available as f64) {
-                height_available + LEFT_SHIFT + 1
-            } else {
-                (id_y as usize) + LEFT_SHIFT
-            };
+    fn get_minor_net(plot: &Rect, axis_x: &Axis, axis_y: &Axis) -> Vec<DisplayPoint> {
+        let mut v: Vec<DisplayPoint> = vec![];
+        for i in 1..(axis_x.interval_count as usize + 1) {
+            let shift = plot.x + ((axis_x.scale_interval_pix * (i as f64)).round() as usize);
+            for j in plot.y..(plot.top() + 1) {
+                if j % 2 != 0 {
+                    v.push(DisplayPoint { x: shift, y: j });
+                }
+            }
+        }
 
-            DisplayPoint { x: id_x, y: id_y }
-        }))
+        for i in 1..(axis_y.interval_count as usize + 1) {
+            let shift = plot.y + ((axis_y.scale_interval_pix * (i as f64)).round() as usize);
+            for j in plot.x..(plot.right() + 1) {
+                if j % 2 != 0 {
+                    v.push(DisplayPoint { x: j, y: shift });
+                }
+            }
+        }
+        v
+    }
 
+    // pixels from the bottom left corner of `plot` to `p`
+    fn plot_offset(p: Point, plot: &Rect, axis_x: &Axis, axis_y: &Axis) -> (f64, f64) {
+        let resolution_x = (axis_x.max_value - axis_x.min_value) / (plot.width as f64);
+        let resolution_y = (axis_y.max_value - axis_y.min_value) / (plot.height as f64);
+        ((p.x - axis_x.min_value) / resolution_x, (p.y - axis_y.min_value) / resolution_y)
     }
+}
 
 
-    fn draw_pixels(&mut self, points: Vec<DisplayPoint>, color: u8) {
-        for p in points {
-            let i = p.y * self.width + p.x;
-            self.pixs[i] = color;
+/// Runs of finite points between the gaps of a serie.
+pub fn finite_parts<I: Iterator<Item = Point>>(points: I) -> Vec<Vec<Point>> {
+    let mut parts = vec![vec![]];
+    for p in points {
+        if p.is_finite() {
+            parts.last_mut().unwrap().push(p);
+        } else if !parts.last().unwrap().is_empty() {
+            parts.push(vec![]);
         }
     }
+    parts.retain(|part| !part.is_empty());
+    parts
+}
+
+// plot of a chart with its axes resolved
+enum Resolved {
+    Cartesian(Rect, Axis, Axis),
+    Polar(PolarPlot),
 }
 
 
+/// Serie of polar `(theta, r)` points as `(x, y)` points, `None` when they
+/// no longer make a serie.
+pub fn to_cartesian<T: IterInPoint<P>, P: InPoint>(serie: &Serie<T, P>) -> Option<Points> {
+    let points = serie.iter
+        .clone()
+        .map(|p| {
+            let p: Point = p.into();
+            Point {
+                x: p.y * p.x.cos(),
+                y: p.y * p.x.sin(),
+            }
+        })
+        .collect::<Vec<Point>>();
+    let converted = Serie::new(points.into_iter(), serie.color()).ok()?;
+    Some(match serie.name() {
+        Some(name) => converted.add_name(name),
+        None => converted,
+    })
+}
+
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use Axis;
+    use std::f64::consts::PI;
 
     #[test]
     fn not_enough_space_test() {
@@ -401,11 +857,67 @@ mod tests {
                    "There are only one unique point. Can't construct line.");
     }
 
+    #[test]
+    fn invalid_color_test() {
+        let result = Chart::new(100, 100, "white", "#000000");
+        assert_eq!(result.err().unwrap().to_string(), "'white' is not a color like #rrggbb.");
+        let result = Serie::new(vec![(0f64, 0f64), (1f64, 1f64)].into_iter(), "#fff");
+        assert_eq!(result.err().unwrap().to_string(), "'#fff' is not a color like #rrggbb.");
+    }
+
+    #[test]
+    fn no_finite_segment_test() {
+        let p = vec![(0f64, f64::NAN), (1f64, 1f64), (2f64, f64::INFINITY), (3f64, 2f64)];
+        let result = Serie::new(p.into_iter(), "#0000ff");
+        assert_eq!(result.err().unwrap().to_string(),
+                   "There are no two finite points in a row. Can't construct line.");
+
+        let p = vec![(0f64, Some(1f64)), (1f64, None), (2f64, Some(3f64)), (3f64, Some(f64::INFINITY)),
+                     (4f64, Some(2f64)), (5f64, Some(1f64))];
+        let serie = Serie::new(p.into_iter(), "#0000ff").unwrap();
+        assert_eq!(serie.bounds(), (Point { x: 0f64, y: 1f64 }, Point { x: 5f64, y: 3f64 }));
+        assert_eq!(finite_parts(serie.iter.clone().map(Into::into)).len(), 3);
+    }
+
+    #[test]
+    fn gaps_break_lines_test() {
+        let p = vec![(0f64, Some(0f64)), (1f64, Some(1f64)), (2f64, None), (3f64, Some(1f64)),
+                     (4f64, Some(0f64))];
+        let serie = Serie::new(p.into_iter(), "#0000ff").unwrap();
+        let chart = Chart::new(200, 150, "#ffffff", "#000000").unwrap();
+        let series = vec![serie];
+
+        let canvas = chart.render(series.clone().into_iter());
+        let blue = canvas.palette().iter().position(|c| *c == Color::from("#0000ff")).unwrap() as u8;
+        let color_at = |x: f64, y: f64| {
+            let pixel = chart.data_to_pixel(series.clone().into_iter(), Point { x, y }).unwrap();
+            canvas.pixels()[pixel.y * 200 + pixel.x]
+        };
+        assert_eq!(color_at(0.5, 0.5), blue);
+        assert_eq!(color_at(3.5, 0.5), blue);
+        // no line from (1, 1) to (3, 1)
+        assert!(color_at(2f64, 1f64) != blue);
+    }
+
+    #[test]
+    fn points_between_gaps_are_dots() {
+        let p = vec![(0f64, Some(0f64)), (1f64, Some(1f64)), (2f64, None), (3f64, Some(3f64)),
+                     (4f64, None)];
+        let serie = Serie::new(p.into_iter(), "#0000ff").unwrap();
+        let chart = Chart::new(200, 150, "#ffffff", "#000000").unwrap();
+        let series = vec![serie];
+
+        let canvas = chart.render(series.clone().into_iter());
+        let blue = canvas.palette().iter().position(|c| *c == Color::from("#0000ff")).unwrap() as u8;
+        let pixel = chart.data_to_pixel(series.into_iter(), Point { x: 3f64, y: 3f64 }).unwrap();
+        assert_eq!(canvas.pixels()[pixel.y * 200 + pixel.x], blue);
+    }
+
     #[test]
     fn can_draw_array() {
         let p = vec![(1f64, 1f64), (2f64, 2f64), (3f64, 3f64)];
         let serie = Serie::new(p.into_iter(), "#0000ff".to_string()).unwrap();
-        let mut chart = Chart::new(100, 100, "#ffffff", "#000000").unwrap();
+        let chart = Chart::new(100, 100, "#ffffff", "#000000").unwrap();
         let series = vec![serie];
         let bmp = chart.draw(series.into_iter());
         for p in bmp {
@@ -418,12 +930,116 @@ mod tests {
         let p = vec![(1f64, 1f64), (2f64, 2f64), (3f64, 3f64)];
         let serie = Serie::new(p.into_iter(), "#0000ff".to_string()).unwrap();
         let axis_x = Axis::new(0f64, 2f64, 7, 2);
-        let mut chart = Chart::new(100, 100, "#ffffff", "#000000")
+        let chart = Chart::new(100, 100, "#ffffff", "#000000")
             .unwrap()
             .add_axis_x(axis_x);
         let series = vec![serie];
         let _ = chart.draw(series.into_iter());
     }
+
+    #[test]
+    fn drawing_twice_gives_same_picture() {
+        let p = vec![(1f64, 1f64), (2f64, 4f64), (3f64, 9f64)];
+        let serie = Serie::new(p.into_iter(), "#0000ff").unwrap().add_name("squares");
+        let chart = Chart::new(100, 100, "#ffffff", "#000000").unwrap().add_title("Title");
+        let series = vec![serie];
+
+        let first = chart.draw(series.clone().into_iter());
+        let second = chart.draw(series.clone().into_iter());
+        assert_eq!(first.len(), second.len());
+        assert!(first == second);
+
+        let canvas = chart.render(series.into_iter());
+        assert_eq!(canvas.palette().len(), 3);
+        assert_eq!(canvas.pixels().len(), 100 * 100);
+    }
+
+    #[test]
+    fn pixel_mapping_test() {
+        let p = vec![(1f64, 1f64), (2f64, 4f64), (3f64, 9f64)];
+        let serie = Serie::new(p.into_iter(), "#0000ff").unwrap();
+        let chart = Chart::new(200, 150, "#ffffff", "#000000").unwrap();
+        let series = vec![serie];
+
+        // the pixel of a point is the one drawn in the serie color
+        let canvas = chart.render(series.clone().into_iter());
+        let blue = canvas.palette().iter().position(|c| *c == Color::from("#0000ff")).unwrap() as u8;
+        let pixel = chart.data_to_pixel(series.clone().into_iter(), Point { x: 2f64, y: 4f64 }).unwrap();
+        assert_eq!(canvas.pixels()[pixel.y * 200 + pixel.x], blue);
+
+        let point = chart.pixel_to_data(series.clone().into_iter(), pixel).unwrap();
+        assert!((point.x - 2f64).abs() < 0.05 && (point.y - 4f64).abs() < 0.1);
+
+        assert_eq!(chart.data_to_pixel(series.clone().into_iter(), Point { x: 100f64, y: 4f64 }), None);
+        assert_eq!(chart.pixel_to_data(series.into_iter(), DisplayPoint { x: 0, y: 0 }), None);
+    }
+
+    #[test]
+    fn lines_leaving_plot_keep_slope() {
+        let p = vec![(-10f64, -20f64), (10f64, 20f64)];
+        let serie = Serie::new(p.into_iter(), "#0000ff").unwrap();
+        let chart = Chart::new(200, 200, "#ffffff", "#000000")
+            .unwrap()
+            .add_axis_x(Axis::new(-2f64, 2f64, 4, 0))
+            .add_axis_y(Axis::new(-2f64, 2f64, 4, 0));
+        let series = vec![serie];
+
+        let canvas = chart.render(series.clone().into_iter());
+        let blue = canvas.palette().iter().position(|c| *c == Color::from("#0000ff")).unwrap() as u8;
+        let drawn = (0..canvas.pixels().len())
+            .filter(|&i| canvas.pixels()[i] == blue)
+            .map(|i| DisplayPoint { x: i % 200, y: i / 200 })
+            .collect::<Vec<DisplayPoint>>();
+        assert!(!drawn.is_empty());
+        for pixel in drawn {
+            let point = chart.pixel_to_data(series.clone().into_iter(), pixel).unwrap();
+            assert!((point.y - 2f64 * point.x).abs() < 0.1, "{:?} is off y = 2x", point);
+        }
+    }
+
+    #[test]
+    fn can_draw_polar() {
+        let rose = polar_points();
+        let serie = Serie::new(rose.into_iter(), "#0000ff").unwrap();
+        let chart = Chart::new(200, 200, "#ffffff", "#000000").unwrap().polar();
+        let mut canvas = Canvas::new(200, 200, "#ffffff");
+        let area = canvas.area();
+        chart.draw_on(&mut canvas, area, vec![serie].into_iter());
+        let blue = canvas.add_color("#0000ff");
+        let blue_count = canvas.pixels().iter().filter(|&&p| p == blue).count();
+        assert!(blue_count > 100);
+    }
+
+    #[test]
+    fn polar_lines_leaving_grid_are_clipped() {
+        let p = vec![(0f64, 10f64), (0.1f64, 10f64), (1f64, 100f64), (PI, 10f64), (PI + 0.1f64, 10f64)];
+        let series = vec![Serie::new(p.into_iter(), "#0000ff").unwrap()];
+        let chart = Chart::new(200, 200, "#ffffff", "#000000")
+            .unwrap()
+            .polar()
+            .add_axis_y(Axis::new(0f64, 20f64, 4, 0));
+
+        let canvas = chart.render(series.clone().into_iter());
+        let blue = canvas.palette().iter().position(|c| *c == Color::from("#0000ff")).unwrap() as u8;
+        let drawn = (0..canvas.pixels().len())
+            .filter(|&i| canvas.pixels()[i] == blue)
+            .map(|i| DisplayPoint { x: i % 200, y: i / 200 })
+            .collect::<Vec<DisplayPoint>>();
+        assert!(!drawn.is_empty());
+        // the segment from (1, 100) to (π, 10) comes nearest at r = 7.95
+        for pixel in drawn {
+            let point = chart.pixel_to_data(series.clone().into_iter(), pixel).unwrap();
+            assert!(point.y > 7.5f64, "{:?} is near the center", point);
+        }
+    }
+
+    fn polar_points() -> Vec<(f64, f64)> {
+        (0..360).map(|i| {
+            let theta = (i as f64) * PI / 180f64;
+            (theta, 10f64 * (2f64 * theta).cos().abs())
+        })
+        .collect()
+    }
 }
 
 #[cfg(all(feature = "dev", test))]
@@ -436,7 +1052,7 @@ mod bench {
         b.iter(|| {
             let p = vec![(1f64, 1f64), (2f64, 2f64), (3f64, 3f64)];
             let serie = Serie::new(p.into_iter(), "#0000ff".to_string()).unwrap();
-            let mut chart = Chart::new(740, 480, "#ffffff", "#000000").unwrap();
+            let chart = Chart::new(740, 480, "#ffffff", "#000000").unwrap();
             let series = vec![serie];
             let _ = chart.draw(series.into_iter());
         })
@@ -447,7 +1063,7 @@ mod bench {
         b.iter(|| {
             let p: Vec<_> = formula!(y(x) = {x*x}, x = [0, 1000; 1]).collect();
             let serie = Serie::new(p.into_iter(), "#0000ff".to_string()).unwrap();
-            let mut chart = Chart::new(740, 480, "#ffffff", "#000000").unwrap();
+            let chart = Chart::new(740, 480, "#ffffff", "#000000").unwrap();
             let series = vec![serie];
             let _ = chart.draw(series.into_iter());
         })
@@ -459,7 +1075,7 @@ mod bench {
         b.iter(|| {
             let p: Vec<_> = formula!(y(x) = {x*x}, x = [0, 1000; 0.001]).collect();
             let serie = Serie::new(p.into_iter(), "#0000ff".to_string()).unwrap();
-            let mut chart = Chart::new(740, 480, "#ffffff", "#000000").unwrap();
+            let chart = Chart::new(740, 480, "#ffffff", "#000000").unwrap();
             let series = vec![serie];
             let _ = chart.draw(series.into_iter());
         })
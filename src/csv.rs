@@ -0,0 +1,295 @@
+use std::f64;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::vec;
+
+use Point;
+use Serie;
+use chart::{GraphError, PALETTE};
+
+
+/// Column of a CSV table, by its header name or index counted from 0.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Column {
+    Name(String),
+    Index(usize),
+}
+
+impl<'a> From<&'a str> for Column {
+    fn from(name: &'a str) -> Column {
+        Column::Name(name.to_string())
+    }
+}
+
+impl From<usize> for Column {
+    fn from(index: usize) -> Column {
+        Column::Index(index)
+    }
+}
+
+
+/// Reads series from CSV data, one serie per y column, sharing the x
+/// column. Fields may be quoted with `"`, doubling it inside the quotes.
+/// Empty fields and `NA` are missing values: the line of a serie breaks at
+/// a missing y, rows missing x are left out.
+///
+/// By default the first row is a header, fields are separated by commas,
+/// x is the first column and every other column is a y column.
+#[derive(Debug, Clone)]
+pub struct CsvReader {
+    delimiter: char,
+    has_header: bool,
+    x: Column,
+    y: Vec<(Column, Option<String>)>,
+}
+
+impl Default for CsvReader {
+    fn default() -> CsvReader {
+        CsvReader::new()
+    }
+}
+
+impl CsvReader {
+    pub fn new() -> CsvReader {
+        CsvReader {
+            delimiter: ',',
+            has_header: true,
+            x: Column::Index(0),
+            y: vec![],
+        }
+    }
+
+    pub fn delimiter(self, delimiter: char) -> CsvReader {
+        CsvReader { delimiter, ..self }
+    }
+
+    /// Whether the first row holds column names rather than values.
+    pub fn has_header(self, has_header: bool) -> CsvReader {
+        CsvReader { has_header, ..self }
+    }
+
+    pub fn x_column<C: Into<Column>>(self, column: C) -> CsvReader {
+        CsvReader { x: column.into(), ..self }
+    }
+
+    /// Adds a y column drawn with `color`.
+    pub fn y_column<C: Into<Column>>(mut self, column: C, color: &str) -> CsvReader {
+        self.y.push((column.into(), Some(color.to_string())));
+        self
+    }
+
+    pub fn read_path<P: AsRef<Path>>(&self,
+                                     path: P)
+                                     -> Result<Vec<Serie<vec::IntoIter<Point>, Point>>, GraphError> {
+        self.read(File::open(path)?)
+    }
+
+    /// Series named after the header of their column, if there is one.
+    pub fn read<R: Read>(&self,
+                         mut reader: R)
+                         -> Result<Vec<Serie<vec::IntoIter<Point>, Point>>, GraphError> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+
+        let mut rows = parse_rows(&text, self.delimiter)?.into_iter();
+        let header = if self.has_header {
+            rows.next().map(|(_, fields)| fields)
+        } else {
+            None
+        };
+        let rows = rows.collect::<Vec<(usize, Vec<String>)>>();
+
+        let x = self.column_index(&self.x, header.as_ref())?;
+        let y = if self.y.is_empty() {
+            let count = header.as_ref()
+                .or_else(|| rows.first().map(|row| &row.1))
+                .map_or(0, |fields| fields.len());
+            (0..count).filter(|&i| i != x).map(|i| (i, None)).collect()
+        } else {
+            let mut y = vec![];
+            for (column, color) in &self.y {
+                y.push((self.column_index(column, header.as_ref())?, color.clone()));
+            }
+            y
+        };
+
+        let mut points = vec![vec![]; y.len()];
+        for &(line, ref fields) in &rows {
+            let x_value = match parse_value(fields, x, line)? {
+                Some(value) => value,
+                None => continue,
+            };
+            for (k, &(column, _)) in y.iter().enumerate() {
+                points[k].push(Point {
+                    x: x_value,
+                    y: parse_value(fields, column, line)?.unwrap_or(f64::NAN),
+                });
+            }
+        }
+
+        let mut series = vec![];
+        for (k, (points, (column, color))) in points.into_iter().zip(y).enumerate() {
+            let color = color.unwrap_or_else(|| PALETTE[k % PALETTE.len()].to_string());
+            let serie = Serie::new(points.into_iter(), color)?;
+            series.push(match header {
+                Some(ref names) => serie.add_name(&*names[column]),
+                None => serie,
+            });
+        }
+        Ok(series)
+    }
+
+    fn column_index(&self, column: &Column, header: Option<&Vec<String>>) -> Result<usize, GraphError> {
+        match *column {
+            // every column has a name when there is a header
+            Column::Index(index) if header.is_some_and(|names| index >= names.len()) => {
+                Err(GraphError::UnknownColumn(index.to_string()))
+            }
+            Column::Index(index) => Ok(index),
+            Column::Name(ref name) => {
+                header.and_then(|names| names.iter().position(|n| n == name))
+                    .ok_or_else(|| GraphError::UnknownColumn(name.clone()))
+            }
+        }
+    }
+}
+
+
+fn parse_value(fields: &[String], column: usize, line: usize) -> Result<Option<f64>, GraphError> {
+    let field = match fields.get(column) {
+        Some(field) => field.trim(),
+        None => {
+            return Err(GraphError::MalformedRow(line,
+                                                format!("there is no column {}, the row has {}",
+                                                        column,
+                                                        fields.len())))
+        }
+    };
+    if field.is_empty() || field == "NA" {
+        return Ok(None);
+    }
+    field.parse::<f64>()
+        .map(Some)
+        .map_err(|_| GraphError::MalformedRow(line, format!("'{}' is not a number", field)))
+}
+
+// non empty rows with the lines they start at, counting from 1
+fn parse_rows(text: &str, delimiter: char) -> Result<Vec<(usize, Vec<String>)>, GraphError> {
+    let mut rows = vec![];
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut row_line = 1;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => quoted = false,
+                c => {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    field.push(c);
+                }
+            }
+        } else if c == delimiter {
+            fields.push(::std::mem::take(&mut field));
+        } else if c == '\n' {
+            fields.push(::std::mem::take(&mut field));
+            push_row(&mut rows, row_line, ::std::mem::take(&mut fields));
+            line += 1;
+            row_line = line;
+        } else if c == '"' && field.trim().is_empty() {
+            field.clear();
+            quoted = true;
+        } else if c != '\r' {
+            field.push(c);
+        }
+    }
+
+    if quoted {
+        return Err(GraphError::MalformedRow(row_line, "unterminated quoted field".to_string()));
+    }
+    fields.push(field);
+    push_row(&mut rows, row_line, fields);
+    Ok(rows)
+}
+
+fn push_row(rows: &mut Vec<(usize, Vec<String>)>, line: usize, fields: Vec<String>) {
+    if fields.len() > 1 || !fields[0].trim().is_empty() {
+        rows.push((line, fields));
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(serie: &Serie<vec::IntoIter<Point>, Point>) -> Vec<(f64, f64)> {
+        serie.iter.clone().map(|p| (p.x, p.y)).collect()
+    }
+
+    #[test]
+    fn columns_by_name_test() {
+        let data = "time,temp,\"wind, m/s\"\n0,15,3\n1,,4\n2,17,NA\n,18,5\n3,19,6\n";
+        let series = CsvReader::new()
+            .x_column("time")
+            .y_column("wind, m/s", "#0000ff")
+            .y_column(1, "#ff0000")
+            .read(data.as_bytes())
+            .unwrap();
+        assert_eq!(series.len(), 2);
+        // missing y values are gaps, the row missing x is left out
+        let gap = |points: Vec<(f64, f64)>| {
+            points.into_iter().map(|(x, y)| (x, if y.is_nan() { None } else { Some(y) })).collect::<Vec<_>>()
+        };
+        assert_eq!(gap(points(&series[0])),
+                   vec![(0f64, Some(3f64)), (1f64, Some(4f64)), (2f64, None), (3f64, Some(6f64))]);
+        assert_eq!(gap(points(&series[1])),
+                   vec![(0f64, Some(15f64)), (1f64, None), (2f64, Some(17f64)), (3f64, Some(19f64))]);
+    }
+
+    #[test]
+    fn quoting_and_delimiter_test() {
+        let data = "1;\"2\"\r\n\n\"3\";\"4\"\"\"";
+        let rows = parse_rows(data, ';').unwrap();
+        assert_eq!(rows,
+                   vec![(1, vec!["1".to_string(), "2".to_string()]),
+                        (3, vec!["3".to_string(), "4\"".to_string()])]);
+
+        let series = CsvReader::new()
+            .delimiter(';')
+            .has_header(false)
+            .read("1;2;5\n3;4;6".as_bytes())
+            .unwrap();
+        assert_eq!(series.len(), 2);
+        assert_eq!(points(&series[1]), vec![(1f64, 5f64), (3f64, 6f64)]);
+    }
+
+    #[test]
+    fn malformed_rows_test() {
+        let error = CsvReader::new().read("x,y\n0,1\n1,abc\n".as_bytes()).err().unwrap();
+        assert_eq!(error.to_string(), "Malformed row at line 3: 'abc' is not a number");
+
+        let error = CsvReader::new().read("x,y\n0,1\n1\n".as_bytes()).err().unwrap();
+        assert_eq!(error.to_string(),
+                   "Malformed row at line 3: there is no column 1, the row has 1");
+
+        let error = CsvReader::new().read("x,y\n0,\"1\n".as_bytes()).err().unwrap();
+        assert_eq!(error.to_string(), "Malformed row at line 2: unterminated quoted field");
+
+        let error = CsvReader::new().y_column("z", "#000000").read("x,y\n".as_bytes()).err().unwrap();
+        assert_eq!(error.to_string(), "There is no column 'z' in data.");
+
+        let error = CsvReader::new().y_column(2, "#000000").read("x,y\n0,1,2\n".as_bytes()).err().unwrap();
+        assert_eq!(error.to_string(), "There is no column '2' in data.");
+    }
+}
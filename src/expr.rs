@@ -0,0 +1,464 @@
+use std::f64;
+use std::fmt;
+
+use Formula;
+use chart::GraphError;
+
+const MAX_NESTING: usize = 256;     //nested parentheses, signs and powers, bounds the recursion
+
+
+/// Math expression of `x` parsed at runtime, like `2 * sin(x) ^ 2 + 1`.
+///
+/// Supports `+`, `-`, `*`, `/`, `^` (right associative, binding tighter
+/// than unary minus), parentheses, the constants `pi` and `e`, and the
+/// functions `sin`, `cos`, `tan`, `exp`, `ln`, `sqrt`, `abs` and
+/// `pow(base, exponent)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression {
+    root: Node,
+}
+
+impl Expression {
+    /// Parses `source`. Errors tell the position of the bad token,
+    /// counting characters from 1.
+    pub fn parse(source: &str) -> Result<Expression, GraphError> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            current: 0,
+            end: source.chars().count() + 1,
+            depth: 0,
+        };
+        let root = parser.parse_sum()?;
+        match parser.peek() {
+            None => Ok(Expression { root }),
+            Some(&(Token::Close, position)) => Err(error(position, "unmatched ')'")),
+            Some(&(ref token, position)) => Err(error(position, &format!("unexpected {}", token))),
+        }
+    }
+
+    /// Value of the expression for `x`, NaN where it is not defined.
+    pub fn eval(&self, x: f64) -> f64 {
+        self.root.eval(x)
+    }
+
+    /// Points `(x, expression(x))` for `x` going from `start` to `stop` by
+    /// `step`, the same way `formula!` samples its expression.
+    pub fn formula(self, start: f64, stop: f64, step: f64) -> Formula<impl Fn(f64) -> f64 + Clone> {
+        Formula::new(start, stop, step, move |x: f64| self.eval(x))
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+}
+
+impl Operator {
+    fn apply(self, a: f64, b: f64) -> f64 {
+        match self {
+            Operator::Add => a + b,
+            Operator::Sub => a - b,
+            Operator::Mul => a * b,
+            Operator::Div => a / b,
+            Operator::Pow => a.powf(b),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Function {
+    Sin,
+    Cos,
+    Tan,
+    Exp,
+    Ln,
+    Sqrt,
+    Abs,
+    Pow,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Function> {
+        match name {
+            "sin" => Some(Function::Sin),
+            "cos" => Some(Function::Cos),
+            "tan" => Some(Function::Tan),
+            "exp" => Some(Function::Exp),
+            "ln" => Some(Function::Ln),
+            "sqrt" => Some(Function::Sqrt),
+            "abs" => Some(Function::Abs),
+            "pow" => Some(Function::Pow),
+            _ => None,
+        }
+    }
+
+    fn arity(self) -> usize {
+        match self {
+            Function::Pow => 2,
+            _ => 1,
+        }
+    }
+
+    fn apply(self, args: &[f64]) -> f64 {
+        let a = args[0];
+        match self {
+            Function::Sin => a.sin(),
+            Function::Cos => a.cos(),
+            Function::Tan => a.tan(),
+            Function::Exp => a.exp(),
+            Function::Ln => a.ln(),
+            Function::Sqrt => a.sqrt(),
+            Function::Abs => a.abs(),
+            Function::Pow => a.powf(args[1]),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Number(f64),
+    Variable,
+    Negate(Box<Node>),
+    Binary(Operator, Box<Node>, Box<Node>),
+    // terms of a sum or factors of a product applied from left to right, kept
+    // flat so that long ones don't nest as deep as they are long
+    Chain(Box<Node>, Vec<(Operator, Node)>),
+    Call(Function, Vec<Node>),
+}
+
+impl Node {
+    fn eval(&self, x: f64) -> f64 {
+        match *self {
+            Node::Number(value) => value,
+            Node::Variable => x,
+            Node::Negate(ref node) => -node.eval(x),
+            Node::Binary(operator, ref left, ref right) => operator.apply(left.eval(x), right.eval(x)),
+            Node::Chain(ref first, ref rest) => {
+                rest.iter().fold(first.eval(x), |a, &(operator, ref node)| operator.apply(a, node.eval(x)))
+            }
+            Node::Call(function, ref args) => {
+                let values = args.iter().map(|arg| arg.eval(x)).collect::<Vec<f64>>();
+                function.apply(&values)
+            }
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Operator(Operator),
+    Open,
+    Close,
+    Comma,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Token::Number(value) => write!(f, "number {}", value),
+            Token::Name(ref name) => write!(f, "'{}'", name),
+            Token::Operator(operator) => {
+                let symbol = match operator {
+                    Operator::Add => '+',
+                    Operator::Sub => '-',
+                    Operator::Mul => '*',
+                    Operator::Div => '/',
+                    Operator::Pow => '^',
+                };
+                write!(f, "'{}'", symbol)
+            }
+            Token::Open => write!(f, "'('"),
+            Token::Close => write!(f, "')'"),
+            Token::Comma => write!(f, "','"),
+        }
+    }
+}
+
+fn chain(first: Node, rest: Vec<(Operator, Node)>) -> Node {
+    if rest.is_empty() {
+        first
+    } else {
+        Node::Chain(Box::new(first), rest)
+    }
+}
+
+fn error(position: usize, message: &str) -> GraphError {
+    GraphError::InvalidExpression(position, message.to_string())
+}
+
+// tokens with the positions they start at
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, GraphError> {
+    let chars = source.chars().collect::<Vec<char>>();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let position = i + 1;
+        let token = match c {
+            ' ' | '\t' | '\n' | '\r' => {
+                i += 1;
+                continue;
+            }
+            '+' => Token::Operator(Operator::Add),
+            '-' => Token::Operator(Operator::Sub),
+            '*' => Token::Operator(Operator::Mul),
+            '/' => Token::Operator(Operator::Div),
+            '^' => Token::Operator(Operator::Pow),
+            '(' => Token::Open,
+            ')' => Token::Close,
+            ',' => Token::Comma,
+            '0'..='9' | '.' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                // exponent, told apart from the constant `e` by the digits after it
+                if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                    let sign = if i + 1 < chars.len() && (chars[i + 1] == '+' || chars[i + 1] == '-') {
+                        1
+                    } else {
+                        0
+                    };
+                    if i + 1 + sign < chars.len() && chars[i + 1 + sign].is_ascii_digit() {
+                        i += 1 + sign;
+                        while i < chars.len() && chars[i].is_ascii_digit() {
+                            i += 1;
+                        }
+                    }
+                }
+                let text = chars[start..i].iter().cloned().collect::<String>();
+                let value = text.parse::<f64>()
+                    .map_err(|_| error(position, &format!("invalid number '{}'", text)))?;
+                tokens.push((Token::Number(value), position));
+                continue;
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                let name = chars[start..i].iter().cloned().collect::<String>();
+                tokens.push((Token::Name(name), position));
+                continue;
+            }
+            c => return Err(error(position, &format!("unexpected character '{}'", c))),
+        };
+        tokens.push((token, position));
+        i += 1;
+    }
+    Ok(tokens)
+}
+
+
+struct Parser<'a> {
+    tokens: &'a [(Token, usize)],
+    current: usize,
+    end: usize,
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a (Token, usize)> {
+        self.tokens.get(self.current)
+    }
+
+    fn next(&mut self) -> Option<&'a (Token, usize)> {
+        let token = self.tokens.get(self.current);
+        self.current += 1;
+        token
+    }
+
+    fn next_operator(&mut self, operators: &[Operator]) -> Option<Operator> {
+        match self.peek() {
+            Some(&(Token::Operator(operator), _)) if operators.contains(&operator) => {
+                self.current += 1;
+                Some(operator)
+            }
+            _ => None,
+        }
+    }
+
+    // sum := product (('+' | '-') product)*
+    fn parse_sum(&mut self) -> Result<Node, GraphError> {
+        let first = self.parse_product()?;
+        let mut rest = vec![];
+        while let Some(operator) = self.next_operator(&[Operator::Add, Operator::Sub]) {
+            rest.push((operator, self.parse_product()?));
+        }
+        Ok(chain(first, rest))
+    }
+
+    // product := unary (('*' | '/') unary)*
+    fn parse_product(&mut self) -> Result<Node, GraphError> {
+        let first = self.parse_unary()?;
+        let mut rest = vec![];
+        while let Some(operator) = self.next_operator(&[Operator::Mul, Operator::Div]) {
+            rest.push((operator, self.parse_unary()?));
+        }
+        Ok(chain(first, rest))
+    }
+
+    // unary := ('-' | '+') unary | power
+    fn parse_unary(&mut self) -> Result<Node, GraphError> {
+        // every nested unary, power, parenthesis and argument passes here
+        if self.depth == MAX_NESTING {
+            let position = self.peek().map_or(self.end, |&(_, position)| position);
+            return Err(error(position, "expression nested too deeply"));
+        }
+        self.depth += 1;
+        let node = match self.next_operator(&[Operator::Add, Operator::Sub]) {
+            Some(Operator::Sub) => self.parse_unary().map(|node| Node::Negate(Box::new(node))),
+            Some(_) => self.parse_unary(),
+            None => self.parse_power(),
+        };
+        self.depth -= 1;
+        node
+    }
+
+    // power := primary ('^' unary)?
+    fn parse_power(&mut self) -> Result<Node, GraphError> {
+        let base = self.parse_primary()?;
+        if self.next_operator(&[Operator::Pow]).is_some() {
+            let exponent = self.parse_unary()?;
+            Ok(Node::Binary(Operator::Pow, Box::new(base), Box::new(exponent)))
+        } else {
+            Ok(base)
+        }
+    }
+
+    // primary := number | name | name '(' sum (',' sum)* ')' | '(' sum ')'
+    fn parse_primary(&mut self) -> Result<Node, GraphError> {
+        match self.next() {
+            None => Err(error(self.end, "unexpected end of expression")),
+            Some(&(Token::Number(value), _)) => Ok(Node::Number(value)),
+            Some(&(Token::Open, position)) => {
+                let node = self.parse_sum()?;
+                self.expect_close(position)?;
+                Ok(node)
+            }
+            Some(&(Token::Name(ref name), position)) => {
+                if let Some(function) = Function::from_name(name) {
+                    self.parse_call(function, name, position)
+                } else {
+                    match name.as_str() {
+                        "x" => Ok(Node::Variable),
+                        "pi" => Ok(Node::Number(f64::consts::PI)),
+                        "e" => Ok(Node::Number(f64::consts::E)),
+                        _ => Err(error(position, &format!("unknown name '{}'", name))),
+                    }
+                }
+            }
+            Some(&(ref token, position)) => Err(error(position, &format!("unexpected {}", token))),
+        }
+    }
+
+    fn parse_call(&mut self, function: Function, name: &str, position: usize) -> Result<Node, GraphError> {
+        let open = match self.next() {
+            Some(&(Token::Open, open)) => open,
+            Some(&(_, position)) => return Err(error(position, &format!("expected '(' after '{}'", name))),
+            None => return Err(error(self.end, &format!("expected '(' after '{}'", name))),
+        };
+        let mut args = vec![self.parse_sum()?];
+        while let Some(&(Token::Comma, _)) = self.peek() {
+            self.current += 1;
+            args.push(self.parse_sum()?);
+        }
+        self.expect_close(open)?;
+        if args.len() != function.arity() {
+            return Err(error(position,
+                             &format!("'{}' takes {} argument(s), {} given",
+                                      name,
+                                      function.arity(),
+                                      args.len())));
+        }
+        Ok(Node::Call(function, args))
+    }
+
+    fn expect_close(&mut self, open: usize) -> Result<(), GraphError> {
+        match self.next() {
+            Some(&(Token::Close, _)) => Ok(()),
+            Some(&(ref token, position)) => Err(error(position, &format!("expected ')', found {}", token))),
+            None => Err(error(open, "unclosed '('")),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(source: &str, x: f64) -> f64 {
+        Expression::parse(source).unwrap().eval(x)
+    }
+
+    fn error_text(source: &str) -> String {
+        Expression::parse(source).err().unwrap().to_string()
+    }
+
+    #[test]
+    fn precedence_test() {
+        assert_eq!(eval("1 + 2 * 3", 0f64), 7f64);
+        assert_eq!(eval("(1 + 2) * 3", 0f64), 9f64);
+        assert_eq!(eval("2 ^ 3 ^ 2", 0f64), 512f64);
+        assert_eq!(eval("-x^2", 3f64), -9f64);
+        assert_eq!(eval("8 / 4 / 2 - 1", 0f64), 0f64);
+        assert_eq!(eval("1.5e1 + 2E-1", 0f64), 15.2f64);
+    }
+
+    #[test]
+    fn functions_and_constants_test() {
+        assert_eq!(eval("sin(pi / 2) + cos(0)", 0f64), 2f64);
+        assert_eq!(eval("ln(e) + sqrt(16) + abs(-x)", 2f64), 7f64);
+        assert_eq!(eval("pow(2, x) * exp(0)", 10f64), 1024f64);
+        assert!(eval("sqrt(x)", -1f64).is_nan());
+    }
+
+    #[test]
+    fn error_position_test() {
+        assert_eq!(error_text("1 + $"),
+                   "Invalid expression at position 5: unexpected character '$'");
+        assert_eq!(error_text("2 * foo(x)"),
+                   "Invalid expression at position 5: unknown name 'foo'");
+        assert_eq!(error_text("sin(x"),
+                   "Invalid expression at position 4: unclosed '('");
+        assert_eq!(error_text("x +"),
+                   "Invalid expression at position 4: unexpected end of expression");
+        assert_eq!(error_text("x) * 2"),
+                   "Invalid expression at position 2: unmatched ')'");
+        assert_eq!(error_text("pow(x)"),
+                   "Invalid expression at position 1: 'pow' takes 2 argument(s), 1 given");
+        assert_eq!(error_text("2 x"),
+                   "Invalid expression at position 3: unexpected 'x'");
+    }
+
+    #[test]
+    fn nesting_is_limited_test() {
+        let nested = format!("{}x{}", "(".repeat(100), ")".repeat(100));
+        assert_eq!(eval(&nested, 2f64), 2f64);
+        assert_eq!(error_text(&"(".repeat(100_000)),
+                   "Invalid expression at position 257: expression nested too deeply");
+        assert_eq!(error_text(&"-".repeat(100_000)),
+                   "Invalid expression at position 257: expression nested too deeply");
+
+        let long = vec!["x"; 200_000].join("+") + &"*x".repeat(200_000);
+        assert_eq!(eval(&long, 1f64), 200_000f64);
+        assert_eq!(eval("1 - 2 - 3 + 8 / 2 / 2", 0f64), -2f64);
+    }
+
+    #[test]
+    fn formula_test() {
+        let points: Vec<_> = Expression::parse("x * x").unwrap().formula(0f64, 2f64, 1f64).collect();
+        let expected: Vec<_> = formula!(y(x) = x * x, x = [0, 2; 1]).collect();
+        assert_eq!(points, expected);
+    }
+}
@@ -0,0 +1,290 @@
+use std::f64;
+use std::io::{self, Write};
+use std::vec;
+
+use Axis;
+use Canvas;
+use Chart;
+use Color;
+use Point;
+use Serie;
+use chart::{GraphError, InPoint, IterInPoint};
+use layout::{Rect, MIN_SIZE};
+
+
+/// Place of a chart in the figure grid, row 0 being the top one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cell {
+    pub row: usize,
+    pub col: usize,
+    pub row_span: usize,
+    pub col_span: usize,
+}
+
+impl Cell {
+    pub fn new(row: usize, col: usize) -> Cell {
+        Cell {
+            row,
+            col,
+            row_span: 1,
+            col_span: 1,
+        }
+    }
+
+    pub fn span(self, row_span: usize, col_span: usize) -> Cell {
+        Cell {
+            row_span,
+            col_span,
+            ..self
+        }
+    }
+}
+
+
+type Series = Vec<Serie<vec::IntoIter<Point>, Point>>;
+
+/// Several charts drawn on one picture, each inside its own cell of a
+/// `rows` x `cols` grid. The size a chart was created with is ignored,
+/// it takes the size of its cell.
+#[derive(Debug)]
+pub struct Figure {
+    width: usize,
+    height: usize,
+    rows: usize,
+    cols: usize,
+    background_color: Color,
+    share_x: bool,
+    share_y: bool,
+    charts: Vec<(Cell, Chart, Series)>,
+}
+
+impl Figure {
+    pub fn new(width: usize,
+               height: usize,
+               rows: usize,
+               cols: usize,
+               background_color: &str)
+               -> Result<Self, GraphError> {
+
+        if rows == 0 || cols == 0 || width / cols < MIN_SIZE || height / rows < MIN_SIZE {
+            return Err(GraphError::NotEnoughSpace);
+        }
+
+        Ok(Figure {
+            width,
+            height,
+            rows,
+            cols,
+            background_color: background_color.parse()?,
+            share_x: false,
+            share_y: false,
+            charts: vec![],
+        })
+    }
+
+    /// Every chart gets the same x axis, calculated from all their series.
+    pub fn share_axis_x(self) -> Figure {
+        Figure { share_x: true, ..self }
+    }
+
+    /// Every chart gets the same y axis, calculated from all their series.
+    pub fn share_axis_y(self) -> Figure {
+        Figure { share_y: true, ..self }
+    }
+
+    pub fn add_chart<S, T, P>(&mut self,
+                              cell: Cell,
+                              chart: Chart,
+                              series: S)
+                              -> Result<(), GraphError>
+        where S: Iterator<Item = Serie<T, P>>,
+              T: IterInPoint<P>,
+              P: InPoint
+    {
+        if cell.row_span == 0 || cell.col_span == 0 || cell.row + cell.row_span > self.rows ||
+           cell.col + cell.col_span > self.cols {
+            return Err(GraphError::CellOutOfGrid);
+        }
+
+        let series = series.map(|s| s.collect_points()).collect();
+        self.charts.push((cell, chart, series));
+        Ok(())
+    }
+
+    /// Rectangle of the picture covered by `cell`.
+    pub fn cell_area(&self, cell: &Cell) -> Rect {
+        let x = cell.col * self.width / self.cols;
+        let right = (cell.col + cell.col_span) * self.width / self.cols;
+        let top = self.height - cell.row * self.height / self.rows;
+        let y = self.height - (cell.row + cell.row_span) * self.height / self.rows;
+        Rect::new(x, y, right - x, top - y)
+    }
+
+    pub fn draw(&self) -> Vec<u8> {
+        self.render().as_vec()
+    }
+
+    /// Draws the figure and streams it to `writer` as a bmp file.
+    pub fn write_to<W: Write>(&self, writer: W) -> io::Result<()> {
+        self.render().write_to(writer)
+    }
+
+    pub fn render(&self) -> Canvas {
+        let mut canvas = Canvas::new(self.width, self.height, self.background_color);
+
+        let axes = self.shared_axes();
+        for (cell, chart, series) in &self.charts {
+            let chart = with_axes(chart, &axes);
+            chart.draw_on(&mut canvas, self.cell_area(cell), series.clone().into_iter());
+        }
+
+        canvas
+    }
+
+    // shared axes fitted to the smallest plot, which is known once the charts
+    // are laid out with axes fitted to their cells
+    fn shared_axes(&self) -> (Option<Axis>, Option<Axis>) {
+        let cells = self.charts.iter().map(|(cell, _, _)| self.cell_area(cell)).collect::<Vec<Rect>>();
+        let axes = self.fit_shared_axes(&cells);
+
+        let plots = self.charts
+            .iter()
+            .zip(cells)
+            .map(|((_, chart, series), area)| {
+                let chart = with_axes(chart, &axes);
+                let legend = series.iter().filter_map(|s| s.name()).map(String::from).collect::<Vec<String>>();
+                let fit = |width, height| chart.fit_axes(series.iter().cloned(), width, height);
+                chart.layout_with(area, &legend, fit).0.plot
+            })
+            .collect::<Vec<Rect>>();
+        self.fit_shared_axes(&plots)
+    }
+
+    fn fit_shared_axes(&self, areas: &[Rect]) -> (Option<Axis>, Option<Axis>) {
+        let axis_x = if self.share_x {
+            self.shared_axis(|p| p.x, areas.iter().map(|r| r.width).min())
+        } else {
+            None
+        };
+        let axis_y = if self.share_y {
+            self.shared_axis(|p| p.y, areas.iter().map(|r| r.height).min())
+        } else {
+            None
+        };
+        (axis_x, axis_y)
+    }
+
+    fn shared_axis<C>(&self, coord: C, size: Option<usize>) -> Option<Axis>
+        where C: Fn(Point) -> f64
+    {
+        let (mut min, mut max) = (f64::INFINITY, f64::NEG_INFINITY);
+
+        for (_, _, series) in &self.charts {
+            for s in series {
+                let (s_min, s_max) = s.bounds();
+                if coord(s_min) < min {
+                    min = coord(s_min);
+                }
+                if coord(s_max) > max {
+                    max = coord(s_max);
+                }
+            }
+        }
+
+        match size {
+            Some(size) if min <= max => Some(Axis::set_axis_auto(max, min, size)),
+            _ => None,
+        }
+    }
+}
+
+
+fn with_axes(chart: &Chart, axes: &(Option<Axis>, Option<Axis>)) -> Chart {
+    let mut chart = chart.clone();
+    if let Some(ref axis) = axes.0 {
+        chart = chart.add_axis_x(axis.clone());
+    }
+    if let Some(ref axis) = axes.1 {
+        chart = chart.add_axis_y(axis.clone());
+    }
+    chart
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn serie(k: f64) -> Serie<vec::IntoIter<(f64, f64)>, (f64, f64)> {
+        let v = vec![(0f64, 0f64), (1f64, k), (2f64, 2f64 * k)];
+        Serie::new(v.into_iter(), "#ff0000").unwrap()
+    }
+
+    fn chart() -> Chart {
+        Chart::new(100, 100, "#ffffff", "#000000").unwrap()
+    }
+
+    #[test]
+    fn cell_out_of_grid_test() {
+        let mut figure = Figure::new(200, 200, 2, 2, "#ffffff").unwrap();
+        let result = figure.add_chart(Cell::new(1, 1).span(1, 2), chart(), vec![serie(1.0)].into_iter());
+        assert_eq!(result.err().unwrap().to_string(),
+                   "Chart cell lies outside of the figure grid.");
+    }
+
+    #[test]
+    fn cell_area_test() {
+        let figure = Figure::new(201, 100, 2, 2, "#ffffff").unwrap();
+        assert_eq!(figure.cell_area(&Cell::new(0, 0)), Rect::new(0, 50, 100, 50));
+        assert_eq!(figure.cell_area(&Cell::new(1, 1)), Rect::new(100, 0, 101, 50));
+        assert_eq!(figure.cell_area(&Cell::new(0, 0).span(2, 2)), Rect::new(0, 0, 201, 100));
+    }
+
+    #[test]
+    fn every_cell_is_drawn_test() {
+        let mut figure = Figure::new(200, 200, 2, 2, "#ffffff").unwrap().share_axis_y();
+        figure.add_chart(Cell::new(0, 0).span(1, 2), chart(), vec![serie(1.0)].into_iter()).unwrap();
+        figure.add_chart(Cell::new(1, 0), chart(), vec![serie(2.0)].into_iter()).unwrap();
+        figure.add_chart(Cell::new(1, 1), chart(), vec![serie(3.0)].into_iter()).unwrap();
+        let canvas = figure.render();
+        let red = canvas.palette().iter().position(|c| *c == "#ff0000".into()).unwrap() as u8;
+
+        for cell in &[Cell::new(0, 0), Cell::new(0, 1), Cell::new(1, 0), Cell::new(1, 1)] {
+            let area = figure.cell_area(cell);
+            let has_red = (area.y..area.top()).any(|y| {
+                (area.x..area.right()).any(|x| canvas.pixels()[y * 200 + x] == red)
+            });
+            assert!(has_red);
+        }
+    }
+
+    #[test]
+    fn shared_axes_are_the_same_test() {
+        let mut figure = Figure::new(600, 400, 2, 3, "#ffffff").unwrap().share_axis_x().share_axis_y();
+        figure.add_chart(Cell::new(0, 0).span(1, 3), chart().add_title("wide"), vec![serie(1.0)].into_iter())
+            .unwrap();
+        figure.add_chart(Cell::new(1, 0), chart(), vec![serie(20.0).add_name("long legend")].into_iter())
+            .unwrap();
+        figure.add_chart(Cell::new(1, 2), chart().add_axis_y_title("y"), vec![serie(-3.0)].into_iter())
+            .unwrap();
+
+        let axes = figure.shared_axes();
+        let (shared_x, shared_y) = (axes.0.clone().unwrap(), axes.1.clone().unwrap());
+        let mut smallest = (figure.width, figure.height);
+        for (cell, chart, series) in &figure.charts {
+            let chart = with_axes(chart, &axes);
+            let legend = series.iter().filter_map(|s| s.name()).map(String::from).collect::<Vec<String>>();
+            let (layout, axis_x, axis_y) = chart.layout_with(figure.cell_area(cell), &legend, |width, height| {
+                chart.fit_axes(series.iter().cloned(), width, height)
+            });
+            for (axis, shared) in [(axis_x, &shared_x), (axis_y, &shared_y)] {
+                assert_eq!(axis.labels(), shared.labels());
+                assert_eq!((axis.min_value, axis.max_value), (shared.min_value, shared.max_value));
+            }
+            smallest = (smallest.0.min(layout.plot.width), smallest.1.min(layout.plot.height));
+        }
+        // fitted to the smallest plot, not to the smallest cell
+        assert_eq!(shared_x.interval_count, Axis::set_axis_auto(2.0, 0.0, smallest.0).interval_count);
+        assert_eq!(shared_y.interval_count, Axis::set_axis_auto(40.0, -6.0, smallest.1).interval_count);
+    }
+}
@@ -0,0 +1,145 @@
+use DisplayPoint;
+
+pub const W_CHAR: usize = 4;     //char width in pixels
+pub const H_CHAR: usize = 5;     //char height in pixels
+pub const SPACE_BETWEEN_CHARS: usize = 1;     //space between chars in pixels
+
+type Glyph = [&'static str; H_CHAR];
+
+const BLANK: Glyph = ["....", "....", "....", "....", "...."];
+
+
+/// Width in pixels occupied by `text` written horizontally.
+pub fn text_width(text: &str) -> usize {
+    let count = text.chars().count();
+    if count == 0 {
+        0
+    } else {
+        count * W_CHAR + (count - 1) * SPACE_BETWEEN_CHARS
+    }
+}
+
+
+/// Points of `text` written left to right, `(x, y)` being its bottom left corner.
+pub fn create_text(text: &str, x: usize, y: usize) -> Vec<DisplayPoint> {
+    let mut v = vec![];
+    let mut char_position = x;
+    for char_ in text.chars() {
+        v.extend(glyph_points(char_).into_iter().map(|(col, row)| {
+            DisplayPoint {
+                x: char_position + col,
+                y: y + H_CHAR - 1 - row,
+            }
+        }));
+        char_position += W_CHAR + SPACE_BETWEEN_CHARS;
+    }
+    v
+}
+
+
+/// Points of `text` written bottom to top, `(x, y)` being its bottom left corner.
+pub fn create_text_vertical(text: &str, x: usize, y: usize) -> Vec<DisplayPoint> {
+    let mut v = vec![];
+    let mut char_position = y;
+    for char_ in text.chars() {
+        v.extend(glyph_points(char_).into_iter().map(|(col, row)| {
+            DisplayPoint {
+                x: x + row,
+                y: char_position + col,
+            }
+        }));
+        char_position += W_CHAR + SPACE_BETWEEN_CHARS;
+    }
+    v
+}
+
+
+// (column, row) of every lit pixel, row 0 being the top one
+fn glyph_points(char_: char) -> Vec<(usize, usize)> {
+    let mut v = vec![];
+    for (row, line) in get_glyph(char_).iter().enumerate() {
+        for (col, pixel) in line.chars().enumerate() {
+            if pixel == '#' {
+                v.push((col, row));
+            }
+        }
+    }
+    v
+}
+
+
+fn get_glyph(char_: char) -> Glyph {
+    match char_.to_ascii_uppercase() {
+        '1' => ["..#.", ".##.", "#.#.", "..#.", "####"],
+        '2' => [".##.", "#..#", "..#.", ".#..", "####"],
+        '3' => [".##.", "#..#", "..#.", "#..#", ".##."],
+        '4' => ["#..#", "#..#", ".###", "...#", "...#"],
+        '5' => ["####", "#...", "###.", "...#", "###."],
+        '6' => [".###", "#...", "###.", "#..#", ".##."],
+        '7' => ["####", "...#", "..#.", ".#..", "#..."],
+        '8' => [".##.", "#..#", ".##.", "#..#", ".##."],
+        '9' => [".##.", "#..#", ".###", "...#", "###."],
+        '0' => [".##.", "#..#", "#..#", "#..#", ".##."],
+        '.' => ["....", "....", "....", "....", ".##."],
+        '-' => ["....", "....", "####", "....", "...."],
+        '+' => ["....", ".#..", "###.", ".#..", "...."],
+        '=' => ["....", "####", "....", "####", "...."],
+        ',' => ["....", "....", "....", ".#..", "#..."],
+        ':' => ["....", ".#..", "....", ".#..", "...."],
+        '(' => ["..#.", ".#..", ".#..", ".#..", "..#."],
+        ')' => [".#..", "..#.", "..#.", "..#.", ".#.."],
+        '/' => ["...#", "..#.", "..#.", ".#..", "#..."],
+        '%' => ["#..#", "...#", "..#.", ".#..", "#..#"],
+        '*' => ["....", "#.#.", ".#..", "#.#.", "...."],
+        '_' => ["....", "....", "....", "....", "####"],
+        '\'' => [".#..", ".#..", "....", "....", "...."],
+        'A' => [".##.", "#..#", "####", "#..#", "#..#"],
+        'B' => ["###.", "#..#", "###.", "#..#", "###."],
+        'C' => [".###", "#...", "#...", "#...", ".###"],
+        'D' => ["###.", "#..#", "#..#", "#..#", "###."],
+        'E' => ["####", "#...", "###.", "#...", "####"],
+        'F' => ["####", "#...", "###.", "#...", "#..."],
+        'G' => [".###", "#...", "#.##", "#..#", ".###"],
+        'H' => ["#..#", "#..#", "####", "#..#", "#..#"],
+        'I' => ["###.", ".#..", ".#..", ".#..", "###."],
+        'J' => ["...#", "...#", "...#", "#..#", ".##."],
+        'K' => ["#..#", "#.#.", "##..", "#.#.", "#..#"],
+        'L' => ["#...", "#...", "#...", "#...", "####"],
+        'M' => ["#..#", "####", "####", "#..#", "#..#"],
+        'N' => ["#..#", "##.#", "#.##", "#..#", "#..#"],
+        'O' => [".##.", "#..#", "#..#", "#..#", ".##."],
+        'P' => ["###.", "#..#", "###.", "#...", "#..."],
+        'Q' => [".##.", "#..#", "#..#", "#.#.", ".#.#"],
+        'R' => ["###.", "#..#", "###.", "#.#.", "#..#"],
+        'S' => [".###", "#...", ".##.", "...#", "###."],
+        'T' => ["####", ".#..", ".#..", ".#..", ".#.."],
+        'U' => ["#..#", "#..#", "#..#", "#..#", ".##."],
+        'V' => ["#..#", "#..#", "#..#", ".##.", ".##."],
+        'W' => ["#..#", "#..#", "####", "####", "#..#"],
+        'X' => ["#..#", "#..#", ".##.", "#..#", "#..#"],
+        'Y' => ["#..#", "#..#", ".##.", ".#..", ".#.."],
+        'Z' => ["####", "...#", ".##.", "#...", "####"],
+        _ => BLANK,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use font;
+
+    #[test]
+    fn text_width_test() {
+        assert_eq!(font::text_width(""), 0);
+        assert_eq!(font::text_width("1"), 4);
+        assert_eq!(font::text_width("-0.25"), 24);
+    }
+
+    #[test]
+    fn vertical_text_is_transposed_test() {
+        let horizontal = font::create_text("7", 0, 0);
+        let vertical = font::create_text_vertical("7", 0, 0);
+        assert_eq!(horizontal.len(), vertical.len());
+        assert!(vertical.iter().all(|p| p.x < font::H_CHAR && p.y < font::W_CHAR));
+    }
+}
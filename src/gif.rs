@@ -0,0 +1,357 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::time::Duration;
+use std::vec;
+
+use Canvas;
+use Chart;
+use Color;
+use Point;
+use Serie;
+use chart::{InPoint, IterInPoint};
+
+const MAX_CODE: u16 = 4096;     //codes of GIF LZW take 12 bits at most
+const MAX_BLOCK: usize = 255;     //bytes of a data sub-block
+const DEFAULT_DELAY: u16 = 10;     //hundredths of a second
+
+
+type Series = Vec<Serie<vec::IntoIter<Point>, Point>>;
+
+/// Frames of a chart drawn one after another as an animated GIF, every
+/// frame a set of series. Axes are calculated from the series of all
+/// frames, unless the chart has them set, so they stay put while data
+/// changes.
+#[derive(Debug)]
+pub struct Animation {
+    chart: Chart,
+    delay: u16,
+    loop_count: u16,
+    frames: Vec<(Series, u16)>,
+}
+
+impl Animation {
+    pub fn new(chart: Chart) -> Animation {
+        Animation {
+            chart,
+            delay: DEFAULT_DELAY,
+            loop_count: 0,
+            frames: vec![],
+        }
+    }
+
+    /// Time frames are shown for, unless they are added with their own.
+    /// GIF counts it in hundredths of a second, 0.1s at first.
+    pub fn delay(self, delay: Duration) -> Animation {
+        Animation { delay: centiseconds(delay), ..self }
+    }
+
+    /// Times the animation is played again, 0 means forever which is the
+    /// default.
+    pub fn loop_count(self, loop_count: u16) -> Animation {
+        Animation { loop_count, ..self }
+    }
+
+    pub fn add_frame<S, T, P>(&mut self, series: S)
+        where S: Iterator<Item = Serie<T, P>>,
+              T: IterInPoint<P>,
+              P: InPoint
+    {
+        let series = series.map(|s| s.collect_points()).collect();
+        self.frames.push((series, self.delay));
+    }
+
+    pub fn add_frame_with_delay<S, T, P>(&mut self, series: S, delay: Duration)
+        where S: Iterator<Item = Serie<T, P>>,
+              T: IterInPoint<P>,
+              P: InPoint
+    {
+        let series = series.map(|s| s.collect_points()).collect();
+        self.frames.push((series, centiseconds(delay)));
+    }
+
+    /// Adds `count` frames, the series of frame `i` given by `frame(i)`.
+    pub fn add_frames<F, S, T, P>(&mut self, count: usize, mut frame: F)
+        where F: FnMut(usize) -> S,
+              S: Iterator<Item = Serie<T, P>>,
+              T: IterInPoint<P>,
+              P: InPoint
+    {
+        for i in 0..count {
+            self.add_frame(frame(i));
+        }
+    }
+
+    /// Every frame drawn with the axes they share.
+    pub fn render(&self) -> Vec<Canvas> {
+        let chart = self.fixed_chart();
+        self.frames
+            .iter()
+            .map(|(series, _)| chart.render(series.clone().into_iter()))
+            .collect()
+    }
+
+    /// Writes the animated GIF, failing if there are no frames or the chart
+    /// is larger than the 65535 pixels a GIF side can have.
+    pub fn write_gif<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        if self.frames.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "animation has no frames"));
+        }
+        if self.chart.width() > u16::MAX as usize || self.chart.height() > u16::MAX as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "chart is too large for a GIF"));
+        }
+        let canvases = self.render();
+
+        // colors of every frame go to the global table while there is room
+        let mut global: Vec<Color> = vec![];
+        for canvas in &canvases {
+            for color in canvas.palette() {
+                if !global.contains(color) && global.len() < 256 {
+                    global.push(*color);
+                }
+            }
+        }
+
+        let (width, height) = (self.chart.width() as u16, self.chart.height() as u16);
+        writer.write_all(b"GIF89a")?;
+        writer.write_all(&[width as u8, (width >> 8) as u8, height as u8, (height >> 8) as u8])?;
+        // global table of 8 bit colors, background of its first color
+        writer.write_all(&[0x80 | 0x70 | (table_bits(global.len()) - 1), 0, 0])?;
+        write_color_table(&global, &mut writer)?;
+
+        // NETSCAPE2.0 looping extension
+        writer.write_all(b"\x21\xff\x0bNETSCAPE2.0\x03\x01")?;
+        writer.write_all(&[self.loop_count as u8, (self.loop_count >> 8) as u8, 0])?;
+
+        for (canvas, &(_, delay)) in canvases.iter().zip(&self.frames) {
+            // graphic control extension holding the delay
+            writer.write_all(&[0x21, 0xf9, 0x04, 0x00, delay as u8, (delay >> 8) as u8, 0, 0])?;
+
+            writer.write_all(&[0x2c, 0, 0, 0, 0])?;
+            writer.write_all(&[width as u8, (width >> 8) as u8, height as u8, (height >> 8) as u8])?;
+
+            let in_global = canvas.palette().iter().all(|c| global.contains(c));
+            let (palette, bits) = if in_global {
+                writer.write_all(&[0])?;
+                (&global[..], table_bits(global.len()))
+            } else {
+                let bits = table_bits(canvas.palette().len());
+                writer.write_all(&[0x80 | (bits - 1)])?;
+                write_color_table(canvas.palette(), &mut writer)?;
+                (canvas.palette(), bits)
+            };
+
+            // GIF rows start from the top
+            let indices = canvas.palette()
+                .iter()
+                .map(|color| palette.iter().position(|c| c == color).unwrap_or(0) as u8)
+                .collect::<Vec<u8>>();
+            let pixels = canvas.pixels()
+                .chunks(canvas.width().max(1))
+                .rev()
+                .flat_map(|row| row.iter().map(|&p| indices[p as usize]))
+                .collect::<Vec<u8>>();
+
+            let min_code_size = bits.max(2);
+            writer.write_all(&[min_code_size])?;
+            for block in lzw_encode(&pixels, min_code_size).chunks(MAX_BLOCK) {
+                writer.write_all(&[block.len() as u8])?;
+                writer.write_all(block)?;
+            }
+            writer.write_all(&[0])?;
+        }
+
+        writer.write_all(&[0x3b])?;
+        writer.flush()
+    }
+
+    fn fixed_chart(&self) -> Chart {
+        let series = self.frames.iter().flat_map(|(series, _)| series.iter().cloned());
+        if series.clone().next().is_none() {
+            return self.chart.clone();
+        }
+        let (axis_x, axis_y) = self.chart.fit_axes(series, self.chart.width(), self.chart.height());
+        self.chart.clone().add_axis_x(axis_x).add_axis_y(axis_y)
+    }
+}
+
+fn centiseconds(delay: Duration) -> u16 {
+    let centiseconds = delay.as_secs() * 100 + (delay.subsec_nanos() / 10_000_000) as u64;
+    centiseconds.min(u16::MAX as u64) as u16
+}
+
+// bits of a color table of 2, 4 .. 256 colors holding `colors`
+fn table_bits(colors: usize) -> u8 {
+    let mut bits = 1;
+    while (1 << bits) < colors {
+        bits += 1;
+    }
+    bits
+}
+
+fn write_color_table<W: Write>(palette: &[Color], writer: &mut W) -> io::Result<()> {
+    for color in palette {
+        writer.write_all(&[color.r, color.g, color.b])?;
+    }
+    for _ in palette.len()..(1 << table_bits(palette.len())) {
+        writer.write_all(&[0, 0, 0])?;
+    }
+    Ok(())
+}
+
+// variable length codes packed from the lowest bit, a clear code first and
+// whenever the 12 bit code table is full
+fn lzw_encode(pixels: &[u8], min_code_size: u8) -> Vec<u8> {
+    let clear = 1u16 << min_code_size;
+    let end = clear + 1;
+    let mut out = vec![];
+    let (mut bits, mut bit_count) = (0u32, 0u32);
+    let mut emit = |code: u16, size: u8, out: &mut Vec<u8>| {
+        bits |= (code as u32) << bit_count;
+        bit_count += size as u32;
+        while bit_count >= 8 {
+            out.push(bits as u8);
+            bits >>= 8;
+            bit_count -= 8;
+        }
+    };
+
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut next = end + 1;
+    let mut size = min_code_size + 1;
+    emit(clear, size, &mut out);
+
+    let mut pixels = pixels.iter();
+    if let Some(&first) = pixels.next() {
+        let mut prefix = first as u16;
+        for &pixel in pixels {
+            if let Some(&code) = table.get(&(prefix, pixel)) {
+                prefix = code;
+                continue;
+            }
+            emit(prefix, size, &mut out);
+            table.insert((prefix, pixel), next);
+            next += 1;
+            if next > (1 << size) && size < 12 {
+                size += 1;
+            }
+            if next == MAX_CODE {
+                emit(clear, size, &mut out);
+                table.clear();
+                next = end + 1;
+                size = min_code_size + 1;
+            }
+            prefix = pixel as u16;
+        }
+        emit(prefix, size, &mut out);
+    }
+    emit(end, size, &mut out);
+    if bit_count > 0 {
+        out.push(bits as u8);
+    }
+    out
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // plain GIF LZW decoder to check the encoder against
+    fn lzw_decode(data: &[u8], min_code_size: u8) -> Vec<u8> {
+        let clear = 1usize << min_code_size;
+        let mut table: Vec<Vec<u8>> = vec![];
+        let mut size = min_code_size + 1;
+        let mut previous: Option<Vec<u8>> = None;
+        let mut out = vec![];
+        let (mut bits, mut bit_count, mut pos) = (0u32, 0u8, 0);
+        loop {
+            while bit_count < size {
+                bits |= (data[pos] as u32) << bit_count;
+                pos += 1;
+                bit_count += 8;
+            }
+            let code = (bits & ((1 << size) - 1)) as usize;
+            bits >>= size;
+            bit_count -= size;
+
+            if code == clear {
+                table = (0..clear + 2).map(|i| vec![i as u8]).collect();
+                size = min_code_size + 1;
+                previous = None;
+                continue;
+            }
+            if code == clear + 1 {
+                return out;
+            }
+            let entry = match (table.get(code).cloned(), previous.as_ref()) {
+                (Some(entry), _) => entry,
+                (None, Some(previous)) => {
+                    let mut entry = previous.clone();
+                    entry.push(previous[0]);
+                    entry
+                }
+                (None, None) => panic!("code {} out of the table", code),
+            };
+            if let Some(mut added) = previous {
+                added.push(entry[0]);
+                table.push(added);
+                if table.len() == 1 << size && size < 12 {
+                    size += 1;
+                }
+            }
+            out.extend_from_slice(&entry);
+            previous = Some(entry);
+        }
+    }
+
+    #[test]
+    fn lzw_round_trip_test() {
+        // long enough to fill the code table a few times
+        let pixels = (0..40000u32).map(|i| ((i * i / 7 + i / 3) % 5) as u8).collect::<Vec<u8>>();
+        assert_eq!(lzw_decode(&lzw_encode(&pixels, 3), 3), pixels);
+        assert_eq!(lzw_decode(&lzw_encode(&[1, 1, 1, 1], 2), 2), vec![1, 1, 1, 1]);
+    }
+
+    #[test]
+    fn axes_are_fixed_test() {
+        let chart = Chart::new(200, 150, "#ffffff", "#000000").unwrap();
+        let mut animation = Animation::new(chart);
+        animation.add_frames(3, |i| {
+            let points = vec![(0f64, 0f64), (1f64, (i + 1) as f64)];
+            vec![Serie::new(points.into_iter(), "#ff0000").unwrap()].into_iter()
+        });
+        let chart = animation.fixed_chart();
+        let axis_y = chart.axis_y().unwrap();
+        assert!(axis_y.min_value <= 0f64 && axis_y.max_value >= 3f64);
+        assert_eq!(animation.render().len(), 3);
+    }
+
+    #[test]
+    fn gif_test() {
+        let chart = Chart::new(100, 100, "#ffffff", "#000000").unwrap();
+        let mut animation = Animation::new(chart).delay(Duration::from_millis(250)).loop_count(2);
+        for k in 1..3 {
+            let points = vec![(0f64, 0f64), (1f64, k as f64)];
+            animation.add_frame(vec![Serie::new(points.into_iter(), "#ff0000").unwrap()].into_iter());
+        }
+
+        let mut gif = vec![];
+        animation.write_gif(&mut gif).unwrap();
+        assert!(gif.starts_with(b"GIF89a\x64\x00\x64\x00"));
+        assert!(gif.ends_with(b"\x00\x3b"));
+        let count = |bytes: &[u8]| gif.windows(bytes.len()).filter(|w| *w == bytes).count();
+        assert_eq!(count(b"NETSCAPE2.0\x03\x01\x02\x00\x00"), 1);
+        // graphic control extensions of 0.25s
+        assert_eq!(count(b"\x21\xf9\x04\x00\x19\x00"), 2);
+
+        assert!(Animation::new(Chart::new(100, 100, "#ffffff", "#000000").unwrap())
+            .write_gif(vec![])
+            .is_err());
+
+        let mut animation = Animation::new(Chart::new(70000, 100, "#ffffff", "#000000").unwrap());
+        animation.add_frame(vec![Serie::new(vec![(0f64, 0f64), (1f64, 1f64)].into_iter(), "#ff0000").unwrap()]
+            .into_iter());
+        let error = animation.write_gif(vec![]).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
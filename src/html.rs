@@ -0,0 +1,366 @@
+use std::io::Write;
+
+use serde_json;
+
+use Chart;
+use Serie;
+use chart::{GraphError, InPoint, IterInPoint};
+use font::H_CHAR;
+use vector::{Drawing, Position, Text};
+
+const DEFAULT_SCALE: usize = 2;
+const FONT_SIZE: usize = H_CHAR + 2;     //capitals about as high as those of the bitmap font
+
+const SCRIPT: &str = r#"(function () {
+  var svg = document.getElementById('chart');
+  var tooltip = document.getElementById('tooltip');
+  var markers = svg.querySelectorAll('.marker');
+  var hidden = {};
+
+  function toPixel(p) {
+    return [chart.left + (p[0] - chart.min_x) / (chart.max_x - chart.min_x) * chart.width,
+            chart.top + (chart.max_y - p[1]) / (chart.max_y - chart.min_y) * chart.height];
+  }
+
+  function escape(text) {
+    return text.replace(/&/g, '&amp;').replace(/</g, '&lt;').replace(/>/g, '&gt;');
+  }
+
+  function hide() {
+    tooltip.style.display = 'none';
+    Array.prototype.forEach.call(markers, function (marker) { marker.style.display = 'none'; });
+  }
+
+  svg.addEventListener('mousemove', function (event) {
+    var point = svg.createSVGPoint();
+    point.x = event.clientX;
+    point.y = event.clientY;
+    var mouse = point.matrixTransform(svg.getScreenCTM().inverse());
+    if (mouse.x < chart.left || mouse.x > chart.left + chart.width ||
+        mouse.y < chart.top || mouse.y > chart.top + chart.height) {
+      hide();
+      return;
+    }
+    var rows = [];
+    chart.series.forEach(function (serie, i) {
+      markers[i].style.display = 'none';
+      if (hidden[i] || serie.points.length === 0) {
+        return;
+      }
+      // nearest point along x
+      var nearest = serie.points[0];
+      serie.points.forEach(function (p) {
+        if (Math.abs(toPixel(p)[0] - mouse.x) < Math.abs(toPixel(nearest)[0] - mouse.x)) {
+          nearest = p;
+        }
+      });
+      var pixel = toPixel(nearest);
+      markers[i].setAttribute('cx', pixel[0]);
+      markers[i].setAttribute('cy', pixel[1]);
+      markers[i].style.display = '';
+      rows.push('<div><span style="color:' + serie.color + '">&#9632;</span> ' +
+                (serie.name === null ? '' : escape(serie.name) + ' ') +
+                '(' + nearest[0] + ', ' + nearest[1] + ')</div>');
+    });
+    tooltip.innerHTML = rows.join('');
+    tooltip.style.display = rows.length ? 'block' : 'none';
+    tooltip.style.left = (event.pageX + 12) + 'px';
+    tooltip.style.top = (event.pageY + 12) + 'px';
+  });
+  svg.addEventListener('mouseleave', hide);
+
+  Array.prototype.forEach.call(svg.querySelectorAll('.legend'), function (entry) {
+    entry.addEventListener('click', function () {
+      var i = entry.getAttribute('data-serie');
+      hidden[i] = !hidden[i];
+      svg.querySelector('.serie[data-serie="' + i + '"]').style.display = hidden[i] ? 'none' : '';
+      entry.style.opacity = hidden[i] ? 0.4 : 1;
+    });
+  });
+})();
+"#;
+
+const STYLE: &str = "body { margin: 0; }
+#tooltip { display: none; position: absolute; padding: 4px 6px; background: #ffffe8;
+           border: 1px solid #888888; font: 12px monospace; pointer-events: none; }
+.legend { cursor: pointer; }
+";
+
+
+// what the script needs to find the nearest points, in svg coordinates
+#[derive(Serialize)]
+struct ChartData<'a> {
+    left: f64,
+    top: f64,
+    width: f64,
+    height: f64,
+    min_x: f64,
+    max_x: f64,
+    min_y: f64,
+    max_y: f64,
+    series: Vec<SerieData<'a>>,
+}
+
+#[derive(Serialize)]
+struct SerieData<'a> {
+    name: Option<&'a str>,
+    color: &'a str,
+    points: &'a [(f64, f64)],
+}
+
+
+/// Renders a chart as a self-contained HTML page holding an SVG drawing
+/// of it. Hovering the plot shows the exact `(x, y)` of the point of every
+/// serie nearest to the pointer, clicking a legend entry hides or shows
+/// its serie. Script and styles are inline, the page fetches nothing.
+///
+/// Layout and axes are calculated the same way `Chart::draw` does. Polar
+/// charts are drawn with their points converted to x and y.
+#[derive(Debug, Clone, Copy)]
+pub struct Html {
+    scale: usize,
+}
+
+impl Default for Html {
+    fn default() -> Html {
+        Html::new()
+    }
+}
+
+impl Html {
+    pub fn new() -> Html {
+        Html { scale: DEFAULT_SCALE }
+    }
+
+    /// Times the drawing is larger than the chart size on the page, 2 at first.
+    pub fn scale(self, scale: usize) -> Html {
+        Html { scale }
+    }
+
+    pub fn render<S, T, P>(&self, chart: &Chart, series: S) -> Result<String, GraphError>
+        where S: Iterator<Item = Serie<T, P>>,
+              T: IterInPoint<P>,
+              P: InPoint
+    {
+        let drawing = Drawing::new(chart, series)?;
+        let svg = Svg { height: drawing.height as f64 };
+        let axis_color = drawing.axis_color.to_string();
+
+        let mut page = String::new();
+        page.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+        page.push_str(&format!("<title>{}</title>\n", escape(chart.title().unwrap_or("Chart"))));
+        page.push_str(&format!("<style>\n{}</style>\n</head>\n<body>\n", STYLE));
+        page.push_str(&format!("<svg id=\"chart\" xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" \
+                                height=\"{}\" viewBox=\"0 0 {} {}\" font-family=\"monospace\" \
+                                font-size=\"{}\">\n",
+                               drawing.width * self.scale,
+                               drawing.height * self.scale,
+                               drawing.width,
+                               drawing.height,
+                               FONT_SIZE));
+        page.push_str(&format!("<rect width=\"{}\" height=\"{}\" fill=\"{}\"/>\n",
+                               drawing.width,
+                               drawing.height,
+                               drawing.background));
+        let clip = drawing.clip();
+        page.push_str(&format!("<clipPath id=\"plot\"><rect x=\"{}\" y=\"{}\" width=\"{}\" \
+                                height=\"{}\"/></clipPath>\n",
+                               clip.x,
+                               svg.y(clip.top() as f64),
+                               clip.width,
+                               clip.height));
+
+        page.push_str(&format!("<g stroke=\"{}\" stroke-dasharray=\"1 1\">\n", axis_color));
+        for &(from, to) in &drawing.grid {
+            page.push_str(&svg.line(from, to));
+        }
+        page.push_str("</g>\n");
+        page.push_str(&format!("<g stroke=\"{}\" stroke-linecap=\"square\">\n", axis_color));
+        for &(from, to) in &drawing.lines {
+            page.push_str(&svg.line(from, to));
+        }
+        page.push_str(&format!("</g>\n<g fill=\"{}\">\n", axis_color));
+        for arrow in &drawing.arrows {
+            page.push_str(&format!("<polygon points=\"{}\"/>\n", svg.points(arrow)));
+        }
+        for text in &drawing.texts {
+            page.push_str(&svg.text(text));
+        }
+        page.push_str("</g>\n");
+
+        page.push_str("<g clip-path=\"url(#plot)\" fill=\"none\" stroke-linecap=\"square\">\n");
+        for (i, line) in drawing.series.iter().enumerate() {
+            page.push_str(&format!("<path class=\"serie\" data-serie=\"{}\" stroke=\"{}\" d=\"{}\"/>\n",
+                                   i,
+                                   line.color,
+                                   svg.path(&line.parts)));
+        }
+        page.push_str("</g>\n");
+
+        for entry in &drawing.legend {
+            let (from, to) = entry.swatch;
+            page.push_str(&format!("<g class=\"legend\" data-serie=\"{}\" fill=\"{}\">\
+                                    <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\
+                                    <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"{}\"/>{}</g>\n",
+                                   entry.serie,
+                                   axis_color,
+                                   entry.area.x,
+                                   svg.y(entry.area.top() as f64),
+                                   entry.area.width,
+                                   entry.area.height,
+                                   drawing.background,
+                                   from.0,
+                                   svg.y(from.1),
+                                   to.0,
+                                   svg.y(to.1),
+                                   drawing.series[entry.serie].color,
+                                   svg.text(&entry.text).trim_end()));
+        }
+
+        for line in &drawing.series {
+            page.push_str(&format!("<circle class=\"marker\" r=\"2\" fill=\"{}\" \
+                                    style=\"display: none\"/>\n",
+                                   line.color));
+        }
+        page.push_str("</svg>\n<div id=\"tooltip\"></div>\n");
+
+        let (left, top) = drawing.data_position(drawing.axis_x.min_value, drawing.axis_y.max_value);
+        let colors = drawing.series.iter().map(|line| line.color.to_string()).collect::<Vec<String>>();
+        let data = ChartData {
+            left,
+            top: svg.y(top),
+            width: drawing.plot.width as f64,
+            height: drawing.plot.height as f64,
+            min_x: drawing.axis_x.min_value,
+            max_x: drawing.axis_x.max_value,
+            min_y: drawing.axis_y.min_value,
+            max_y: drawing.axis_y.max_value,
+            series: drawing.series
+                .iter()
+                .zip(&colors)
+                .map(|(line, color)| {
+                    SerieData {
+                        name: line.name.as_deref(),
+                        color,
+                        points: &line.values,
+                    }
+                })
+                .collect(),
+        };
+        let json = serde_json::to_string(&data).expect("chart data is plain numbers and strings");
+        // `</script>` in a serie name mustn't end the script
+        page.push_str(&format!("<script>\nvar chart = {};\n{}</script>\n",
+                               json.replace("</", "<\\/"),
+                               SCRIPT));
+        page.push_str("</body>\n</html>\n");
+        Ok(page)
+    }
+
+    pub fn write_to<S, T, P, W>(&self, chart: &Chart, series: S, mut writer: W) -> Result<(), GraphError>
+        where S: Iterator<Item = Serie<T, P>>,
+              T: IterInPoint<P>,
+              P: InPoint,
+              W: Write
+    {
+        writer.write_all(self.render(chart, series)?.as_bytes())?;
+        Ok(())
+    }
+}
+
+
+// positions of the drawing, y going up, turned into svg coordinates which
+// go down from the top
+struct Svg {
+    height: f64,
+}
+
+impl Svg {
+    fn y(&self, y: f64) -> f64 {
+        self.height - y
+    }
+
+    fn line(&self, from: Position, to: Position) -> String {
+        format!("<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"/>\n",
+                from.0,
+                self.y(from.1),
+                to.0,
+                self.y(to.1))
+    }
+
+    fn points(&self, points: &[Position]) -> String {
+        points.iter()
+            .map(|&(x, y)| format!("{:.2},{:.2}", x, self.y(y)))
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    // a move to the start of every part, the line breaking between them
+    fn path(&self, parts: &[Vec<Position>]) -> String {
+        parts.iter()
+            .map(|part| format!("M{}", self.points(part)))
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    // text stretched over the width the bitmap font takes
+    fn text(&self, text: &Text) -> String {
+        let (x, y) = text.position;
+        let transform = if text.vertical {
+            format!("transform=\"translate({} {}) rotate(-90)\"", x, self.y(y))
+        } else {
+            format!("x=\"{}\" y=\"{}\"", x, self.y(y))
+        };
+        format!("<text {} textLength=\"{}\" lengthAdjust=\"spacingAndGlyphs\">{}</text>\n",
+                transform,
+                text.width,
+                escape(&text.text))
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Point;
+    use chart::Points;
+
+    fn serie(name: &str, color: &str) -> Points {
+        let points = vec![Point { x: 0f64, y: 0f64 }, Point { x: 1f64, y: 2f64 }];
+        Serie::new(points.into_iter(), color).unwrap().add_name(name)
+    }
+
+    #[test]
+    fn page_is_self_contained_test() {
+        let chart = Chart::new(200, 150, "#ffffff", "#000000").unwrap().add_title("A & B");
+        let page = Html::new()
+            .render(&chart, vec![serie("sin", "#ff0000"), serie("cos", "#0000ff")].into_iter())
+            .unwrap();
+
+        assert!(page.contains("width=\"400\" height=\"300\" viewBox=\"0 0 200 150\""));
+        assert!(page.contains(">A &amp; B</text>"));
+        assert_eq!(page.matches("<path class=\"serie\"").count(), 2);
+        assert_eq!(page.matches("<g class=\"legend\"").count(), 2);
+        assert_eq!(page.matches("<circle class=\"marker\"").count(), 2);
+        assert!(page.contains("\"points\":[[0.0,0.0],[1.0,2.0]]"));
+        // nothing is fetched
+        assert!(!page.contains(" src=") && !page.contains(" href="));
+    }
+
+    #[test]
+    fn names_are_escaped_test() {
+        let chart = Chart::new(200, 150, "#ffffff", "#000000").unwrap();
+        let page = Html::new().render(&chart, vec![serie("</script>", "#ff0000")].into_iter()).unwrap();
+        assert_eq!(page.matches("</script>").count(), 1);
+        assert!(page.contains("&lt;/script&gt;"));
+
+        let empty: Vec<Points> = vec![];
+        assert!(Html::new().render(&chart, empty.into_iter()).is_err());
+    }
+}
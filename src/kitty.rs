@@ -0,0 +1,93 @@
+use std::io::{self, Write};
+
+use Canvas;
+
+const CHUNK_SIZE: usize = 4096;     //largest base64 payload of one escape sequence
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+
+/// Writes `canvas` as Kitty graphics protocol escape sequences which
+/// transmit and display it as 24 bit RGB pixels at the cursor.
+pub fn write_kitty<W: Write>(canvas: &Canvas, writer: &mut W) -> io::Result<()> {
+    let (width, height) = (canvas.width(), canvas.height());
+    let palette = canvas.palette();
+    let pixels = canvas.pixels();
+
+    // rows of the canvas start from the bottom, kitty ones from the top
+    let mut rgb = Vec::with_capacity(width * height * 3);
+    for y in (0..height).rev() {
+        for &p in &pixels[y * width..(y + 1) * width] {
+            let color = palette[p as usize];
+            rgb.extend_from_slice(&[color.r, color.g, color.b]);
+        }
+    }
+
+    let payload = base64(&rgb);
+    let chunks = payload.chunks(CHUNK_SIZE).collect::<Vec<&[u8]>>();
+    if chunks.is_empty() {
+        return write!(writer, "\x1b_Ga=T,f=24,s={},v={};\x1b\\", width, height);
+    }
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = if i + 1 < chunks.len() { 1 } else { 0 };
+        if i == 0 {
+            write!(writer, "\x1b_Ga=T,f=24,s={},v={},m={};", width, height, more)?;
+        } else {
+            write!(writer, "\x1b_Gm={};", more)?;
+        }
+        writer.write_all(chunk)?;
+        writer.write_all(b"\x1b\\")?;
+    }
+    Ok(())
+}
+
+fn base64(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len().div_ceil(3) * 4);
+    for group in data.chunks(3) {
+        let b = [group[0], *group.get(1).unwrap_or(&0), *group.get(2).unwrap_or(&0)];
+        let n = (b[0] as usize) << 16 | (b[1] as usize) << 8 | b[2] as usize;
+        out.push(BASE64[n >> 18 & 63]);
+        out.push(BASE64[n >> 12 & 63]);
+        out.push(if group.len() > 1 { BASE64[n >> 6 & 63] } else { b'=' });
+        out.push(if group.len() > 2 { BASE64[n & 63] } else { b'=' });
+    }
+    out
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use DisplayPoint;
+
+    #[test]
+    fn base64_test() {
+        assert_eq!(base64(b"Man"), b"TWFu");
+        assert_eq!(base64(b"Ma"), b"TWE=");
+        assert_eq!(base64(b"M"), b"TQ==");
+    }
+
+    #[test]
+    fn kitty_test() {
+        let mut canvas = Canvas::new(1, 2, "#000000");
+        let white = canvas.add_color("#ffffff");
+        canvas.draw_pixels(vec![DisplayPoint { x: 0, y: 1 }], white);
+
+        let mut out = vec![];
+        write_kitty(&canvas, &mut out).unwrap();
+        // the top pixel comes first
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "\x1b_Ga=T,f=24,s=1,v=2,m=0;////AAAA\x1b\\");
+    }
+
+    #[test]
+    fn payload_is_chunked_test() {
+        let canvas = Canvas::new(100, 100, "#123456");
+        let mut out = vec![];
+        write_kitty(&canvas, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        // 30000 bytes take 40000 base64 characters, ten chunks
+        assert_eq!(text.matches("\x1b_G").count(), 10);
+        assert_eq!(text.matches("m=1;").count(), 9);
+        assert!(text.contains("\x1b_Gm=0;"));
+    }
+}
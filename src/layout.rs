@@ -0,0 +1,243 @@
+use DisplayPoint;
+use font::{self, H_CHAR, W_CHAR};
+
+pub const W_BORDER: usize = 1;     //space around graph width
+pub const W_GAP: usize = 1;     //space between a text and what it describes
+pub const W_TICK: usize = 3;     //tick mark length
+pub const W_ARROW: usize = 4;      //width of arrow
+pub const H_ARROW_HALF: usize = 3;     //half arrow height
+const W_SWATCH: usize = 8;     //legend colour sample width
+const H_TITLE_GAP: usize = 3;     //space under the chart title
+
+/// Smallest width and height a chart can be laid out in.
+pub const MIN_SIZE: usize = 2 * H_CHAR + 2 * W_CHAR + W_ARROW + 2 * W_BORDER;
+
+
+/// Rectangle in pixels, `(x, y)` being its bottom left corner.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn right(&self) -> usize {
+        self.x + self.width
+    }
+
+    pub fn top(&self) -> usize {
+        self.y + self.height
+    }
+
+    pub fn contains(&self, p: DisplayPoint) -> bool {
+        p.x >= self.x && p.x <= self.right() && p.y >= self.y && p.y <= self.top()
+    }
+}
+
+
+/// Every text element that takes space around the plot.
+#[derive(Debug, Clone, Copy)]
+pub struct Texts<'a> {
+    pub title: Option<&'a str>,
+    pub axis_x_title: Option<&'a str>,
+    pub axis_y_title: Option<&'a str>,
+    pub ticks_x: &'a [String],
+    pub ticks_y: &'a [String],
+    pub legend: &'a [String],
+}
+
+
+/// Where each part of a chart goes inside the area given to it.
+///
+/// `plot` is the rectangle series are drawn in, its left and bottom edges
+/// are the axis lines. The other rectangles are present only when there
+/// is something to put in them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layout {
+    pub area: Rect,
+    pub plot: Rect,
+    pub title: Option<Rect>,
+    pub axis_x_title: Option<Rect>,
+    pub axis_y_title: Option<Rect>,
+    pub legend: Option<Rect>,
+}
+
+impl Layout {
+    pub fn new(area: Rect, texts: &Texts) -> Layout {
+        let title_height = texts.title.map_or(0, |_| H_CHAR + H_TITLE_GAP);
+        let axis_x_title_height = texts.axis_x_title.map_or(0, |_| H_CHAR + W_GAP);
+        let axis_y_title_width = texts.axis_y_title.map_or(0, |_| H_CHAR + W_GAP);
+
+        let max_tick_y = max_width(texts.ticks_y);
+        let first_tick_x = texts.ticks_x.first().map_or(0, |s| font::text_width(s));
+        let last_tick_x = texts.ticks_x.last().map_or(0, |s| font::text_width(s));
+
+        let legend_width = if texts.legend.is_empty() {
+            0
+        } else {
+            W_SWATCH + 2 * W_GAP + max_width(texts.legend)
+        };
+        let legend_height = (H_CHAR + W_GAP) * texts.legend.len();
+
+        let left = W_BORDER +
+                   max(axis_y_title_width + max_tick_y + W_GAP + W_TICK,
+                       first_tick_x / 2);
+        let bottom = W_BORDER + axis_x_title_height + H_CHAR + W_GAP + W_TICK;
+        let top = W_BORDER + title_height + W_ARROW;
+        let legend_space = if legend_width > 0 {
+            2 * W_GAP + legend_width
+        } else {
+            0
+        };
+        let right = W_BORDER + max(W_ARROW, last_tick_x - last_tick_x / 2) + legend_space;
+
+        let plot = Rect::new(area.x + left,
+                             area.y + bottom,
+                             area.width.saturating_sub(left + right),
+                             area.height.saturating_sub(top + bottom));
+
+        let title = texts.title.map(|s| {
+            let width = font::text_width(s);
+            Rect::new(area.x + area.width.saturating_sub(width) / 2,
+                      area.top().saturating_sub(W_BORDER + H_CHAR),
+                      width,
+                      H_CHAR)
+        });
+
+        let axis_x_title = texts.axis_x_title.map(|s| {
+            let width = font::text_width(s);
+            Rect::new((plot.x + plot.width / 2).saturating_sub(width / 2),
+                      area.y + W_BORDER,
+                      width,
+                      H_CHAR)
+        });
+
+        let axis_y_title = texts.axis_y_title.map(|s| {
+            let height = font::text_width(s);
+            Rect::new(area.x + W_BORDER,
+                      (plot.y + plot.height / 2).saturating_sub(height / 2),
+                      H_CHAR,
+                      height)
+        });
+
+        let legend = if legend_width > 0 {
+            Some(Rect::new(area.right().saturating_sub(W_BORDER + legend_width),
+                           plot.top().saturating_sub(legend_height),
+                           legend_width,
+                           legend_height))
+        } else {
+            None
+        };
+
+        Layout {
+            area,
+            plot,
+            title,
+            axis_x_title,
+            axis_y_title,
+            legend,
+        }
+    }
+
+    /// Rectangle holding the colour sample and the name of `i`-th legend entry.
+    pub fn legend_entry(&self, i: usize) -> Option<(Rect, Rect)> {
+        self.legend.map(|legend| {
+            let y = legend.top().saturating_sub((H_CHAR + W_GAP) * (i + 1));
+            let swatch = Rect::new(legend.x, y + H_CHAR / 2, W_SWATCH, 0);
+            let text = Rect::new(legend.x + W_SWATCH + 2 * W_GAP,
+                                 y,
+                                 legend.width.saturating_sub(W_SWATCH + 2 * W_GAP),
+                                 H_CHAR);
+            (swatch, text)
+        })
+    }
+}
+
+
+fn max(a: usize, b: usize) -> usize {
+    if a > b { a } else { b }
+}
+
+fn max_width(texts: &[String]) -> usize {
+    texts.iter().map(|s| font::text_width(s)).fold(0, max)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use font;
+    use layout::*;
+
+    fn ticks(v: &[&str]) -> Vec<String> {
+        v.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn wide_tick_labels_widen_left_margin_test() {
+        let area = Rect::new(0, 0, 200, 100);
+        let ticks_x = ticks(&["0", "1"]);
+        let narrow = ticks(&["0", "1"]);
+        let wide = ticks(&["-1000.25", "1000.25"]);
+        let texts = Texts {
+            title: None,
+            axis_x_title: None,
+            axis_y_title: None,
+            ticks_x: &ticks_x,
+            ticks_y: &narrow,
+            legend: &[],
+        };
+        let narrow_layout = Layout::new(area, &texts);
+        let wide_layout = Layout::new(area, &Texts { ticks_y: &wide, ..texts });
+
+        assert_eq!(narrow_layout.plot.x, 1 + 4 + 1 + 3);
+        assert_eq!(wide_layout.plot.x, 1 + font::text_width("-1000.25") + 1 + 3);
+        assert_eq!(wide_layout.plot.right(), narrow_layout.plot.right());
+    }
+
+    #[test]
+    fn titles_and_legend_take_space_test() {
+        let area = Rect::new(0, 0, 200, 100);
+        let t = ticks(&["0", "1"]);
+        let legend = ticks(&["SIN", "COS"]);
+        let texts = Texts {
+            title: None,
+            axis_x_title: None,
+            axis_y_title: None,
+            ticks_x: &t,
+            ticks_y: &t,
+            legend: &[],
+        };
+        let bare = Layout::new(area, &texts);
+        let full = Layout::new(area,
+                               &Texts {
+                                   title: Some("TITLE"),
+                                   axis_x_title: Some("X"),
+                                   axis_y_title: Some("Y"),
+                                   legend: &legend,
+                                   ..texts
+                               });
+
+        assert!(full.plot.top() < bare.plot.top());
+        assert!(full.plot.y > bare.plot.y);
+        assert!(full.plot.x > bare.plot.x);
+        assert!(full.plot.right() < bare.plot.right());
+
+        let legend = full.legend.unwrap();
+        assert!(legend.x > full.plot.right());
+        assert!(legend.right() < area.right());
+        assert!(full.title.unwrap().y > full.plot.top());
+        assert!(full.axis_y_title.unwrap().right() < full.plot.x);
+        assert!(full.axis_x_title.unwrap().top() < full.plot.y);
+    }
+}
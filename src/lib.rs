@@ -1,25 +1,65 @@
 #![cfg_attr(feature = "dev", allow(unstable_features, unused_features))]
 #![cfg_attr(feature = "dev", feature(plugin, test))]
 #![cfg_attr(feature = "dev", plugin(clippy))]
+// struct fields are initialized as `field: field`
+#![allow(clippy::redundant_field_names, clippy::needless_lifetimes)]
 
 extern crate byteorder;
 #[macro_use]
 extern crate quick_error;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate toml;
 #[macro_use]
 pub mod macros;
 pub mod chart;
 pub mod bitmap;
 mod line;
-mod flatmappairs;
 mod axis;
 mod tick;
+mod font;
+mod layout;
+mod canvas;
+mod figure;
+mod sampler;
+mod polar;
+mod expr;
+mod csv;
+mod pairs;
+mod braille;
+mod sixel;
+mod kitty;
+mod gif;
+mod html;
+mod vector;
+mod postscript;
+mod spec;
 
 
 pub use self::bitmap::BitMap;
+pub use self::bitmap::{BmpEncoder, Compression, NetpbmEncoding};
 pub use self::bitmap::Color;
 pub use self::chart::DisplayPoint;
 pub use self::chart::Point;
 pub use self::chart::Chart;
+pub use self::chart::Coordinates;
 pub use self::chart::Serie;
+pub use self::chart::PALETTE;
 pub use self::axis::Axis;
-pub use self::macros::Formula;
+pub use self::canvas::Canvas;
+pub use self::figure::{Cell, Figure};
+pub use self::layout::{Layout, Rect};
+pub use self::macros::{Formula, Parametric, Polar};
+pub use self::sampler::AdaptiveSampler;
+pub use self::expr::Expression;
+pub use self::csv::{Column, CsvReader};
+pub use self::pairs::read_pairs;
+pub use self::braille::{AnsiColors, Braille};
+pub use self::sixel::write_sixel;
+pub use self::kitty::write_kitty;
+pub use self::gif::Animation;
+pub use self::html::Html;
+pub use self::postscript::{write_eps, write_pdf};
+pub use self::spec::{AxisSpec, ChartSpec, FormulaSpec, SerieSpec};
@@ -1,6 +1,4 @@
 use DisplayPoint;
-use flatmappairs::FlatMapPairs;
-use std::iter::once;
 
 pub struct Line {
     first: DisplayPoint,
@@ -92,10 +90,99 @@ impl Iterator for Line {
     }
 }
 
-pub fn extrapolate<'a>(points: Box<Iterator<Item = DisplayPoint> + 'a>)
-                       -> Box<Iterator<Item = DisplayPoint> + 'a> {
+/// Pixels of the polyline through `points`, which are in pixels from the
+/// corner of a `width` x `height` plot. Every segment is clipped to the plot
+/// before it is rasterized, so lines leaving it keep their slope; segments
+/// with a non finite end are left out. A single point, or one between non
+/// finite ones, is drawn as a dot.
+pub fn clip_polyline<I>(points: I, width: usize, height: usize) -> Vec<DisplayPoint>
+    where I: Iterator<Item = (f64, f64)>
+{
+    let pixel = |p: (f64, f64)| {
+        DisplayPoint {
+            x: p.0.round() as usize,
+            y: p.1.round() as usize,
+        }
+    };
+    let points = points.collect::<Vec<(f64, f64)>>();
+    let finite = |i: usize| points.get(i).is_some_and(|p| p.0.is_finite() && p.1.is_finite());
+    let mut pixels = vec![];
+    for (i, &p) in points.iter().enumerate() {
+        // a point alone or between gaps is a dot, drawn when it lies inside
+        if !finite(i.wrapping_sub(1)) && !finite(i + 1) {
+            pixels.extend(clip(p, p, width as f64, height as f64).map(|(a, _)| pixel(a)));
+        }
+    }
+    for pair in points.windows(2) {
+        if let Some((a, b)) = clip(pair[0], pair[1], width as f64, height as f64) {
+            let (a, b) = (pixel(a), pixel(b));
+            pixels.push(a);
+            pixels.extend(Line::new(a, b));
+            pixels.push(b);
+        }
+    }
+    pixels
+}
+
+// Liang-Barsky: the part of the segment at `t` in [t0, t1] lies inside
+// when `p * t <= q` holds for each of the four borders
+fn clip(from: (f64, f64), to: (f64, f64), width: f64, height: f64) -> Option<((f64, f64), (f64, f64))> {
+    if !(from.0.is_finite() && from.1.is_finite() && to.0.is_finite() && to.1.is_finite()) {
+        return None;
+    }
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let (mut t0, mut t1) = (0f64, 1f64);
+    for &(p, q) in &[(-dx, from.0), (dx, width - from.0), (-dy, from.1), (dy, height - from.1)] {
+        if p == 0f64 {
+            // parallel to the border, outside of it or not at all
+            if q < 0f64 {
+                return None;
+            }
+        } else if p < 0f64 {
+            t0 = t0.max(q / p);
+        } else {
+            t1 = t1.min(q / p);
+        }
+    }
+    if t0 > t1 {
+        return None;
+    }
+    Some(((from.0 + t0 * dx, from.1 + t0 * dy), (from.0 + t1 * dx, from.1 + t1 * dy)))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let it = FlatMapPairs::new(points,
-                               |a: DisplayPoint, b: DisplayPoint| once(a).chain(Line::new(a, b)));
-    Box::new(it)
+    #[test]
+    fn clip_test() {
+        assert_eq!(clip((1f64, 2f64), (3f64, 4f64), 10f64, 10f64), Some(((1f64, 2f64), (3f64, 4f64))));
+        // crossing the plot, the slope stays
+        assert_eq!(clip((-5f64, 0f64), (15f64, 10f64), 10f64, 10f64),
+                   Some(((0f64, 2.5f64), (10f64, 7.5f64))));
+        assert_eq!(clip((-5f64, 0f64), (-1f64, 10f64), 10f64, 10f64), None);
+        assert_eq!(clip((0f64, 12f64), (10f64, 12f64), 10f64, 10f64), None);
+        assert_eq!(clip((1f64, f64::NAN), (3f64, 4f64), 10f64, 10f64), None);
+    }
+
+    #[test]
+    fn polyline_is_clipped_test() {
+        let points = vec![(-10f64, -20f64), (10f64, 20f64)];
+        let pixels = clip_polyline(points.into_iter(), 10, 10);
+        assert!(pixels.contains(&DisplayPoint { x: 0, y: 0 }));
+        assert!(pixels.contains(&DisplayPoint { x: 5, y: 10 }));
+        assert!(pixels.iter().all(|p| p.x <= 5 && p.y <= 10));
+    }
+
+    #[test]
+    fn lone_point_is_drawn_test() {
+        assert_eq!(clip_polyline(vec![(2.4f64, 3.6f64)].into_iter(), 10, 10),
+                   vec![DisplayPoint { x: 2, y: 4 }]);
+        assert!(clip_polyline(vec![(12f64, 3f64)].into_iter(), 10, 10).is_empty());
+        let points = vec![(1f64, f64::NAN), (2f64, 2f64), (3f64, f64::NAN), (4f64, 4f64), (5f64, 5f64)];
+        let pixels = clip_polyline(points.into_iter(), 10, 10);
+        assert_eq!(pixels[0], DisplayPoint { x: 2, y: 2 });
+        assert!(pixels[1..].iter().all(|p| p.x >= 4));
+    }
 }
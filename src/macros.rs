@@ -1,18 +1,25 @@
 
 const EPSILON: f64 = 0.0000001;
 
-type TypeFn = fn(x: f64) -> f64;
+pub type TypeFn = fn(x: f64) -> f64;
 
-pub struct Formula {
+/// Points `(x, f(x))` for `x` going from `start` to `stop` by `step`.
+///
+/// `f` may be a plain function as well as a closure capturing its
+/// environment. The formula stays `Clone` as long as `f` is.
+#[derive(Clone, Copy)]
+pub struct Formula<F = TypeFn>
+    where F: Fn(f64) -> f64
+{
     pub current_x: f64,
     pub stop: f64,
     pub start: f64,
     pub step: f64,
-    pub f: TypeFn,
+    pub f: F,
 }
 
-impl Formula {
-    pub fn new(start: f64, stop: f64, step: f64, f: TypeFn) -> Self {
+impl<F: Fn(f64) -> f64> Formula<F> {
+    pub fn new(start: f64, stop: f64, step: f64, f: F) -> Self {
         Formula {
             current_x: start,
             stop: stop,
@@ -24,46 +31,125 @@ impl Formula {
 }
 
 
-impl Iterator for Formula {
+impl<F: Fn(f64) -> f64> Iterator for Formula<F> {
     type Item = (f64, f64);
 
     #[inline]
     fn next(&mut self) -> Option<(f64, f64)> {
-        if self.start < self.stop {
-            if self.current_x < self.stop {
-                let x = self.current_x;
-                self.current_x += self.step;
-                Some((x, (self.f)(x)))
-            } else if (self.stop - self.current_x).abs() < EPSILON {
-                let x = self.stop;
-                self.current_x += self.step;
-                Some((x, (self.f)(x)))
-            } else {
-                None
-            }
-        } else if self.current_x > self.stop {
-            let x = self.current_x;
-            self.current_x -= self.step;
-            Some((x, (self.f)(x)))
-        } else if (self.stop - self.current_x).abs() < EPSILON {
-            let x = self.stop;
-            self.current_x -= self.step;
-            Some((x, (self.f)(x)))
-        } else {
-            None
+        next_argument(&mut self.current_x, self.start, self.stop, self.step)
+            .map(|x| (x, (self.f)(x)))
+    }
+}
+
+
+/// Points `(x(t), y(t))` for `t` going from `start` to `stop` by `step`.
+#[derive(Clone, Copy)]
+pub struct Parametric<FX = TypeFn, FY = TypeFn>
+    where FX: Fn(f64) -> f64,
+          FY: Fn(f64) -> f64
+{
+    pub current_t: f64,
+    pub stop: f64,
+    pub start: f64,
+    pub step: f64,
+    pub fx: FX,
+    pub fy: FY,
+}
+
+impl<FX: Fn(f64) -> f64, FY: Fn(f64) -> f64> Parametric<FX, FY> {
+    pub fn new(start: f64, stop: f64, step: f64, fx: FX, fy: FY) -> Self {
+        Parametric {
+            current_t: start,
+            stop,
+            start,
+            step,
+            fx,
+            fy,
         }
     }
 }
 
-impl Copy for Formula {}
+impl<FX: Fn(f64) -> f64, FY: Fn(f64) -> f64> Iterator for Parametric<FX, FY> {
+    type Item = (f64, f64);
 
-impl Clone for Formula {
-    fn clone(&self) -> Self {
-        *self
+    #[inline]
+    fn next(&mut self) -> Option<(f64, f64)> {
+        next_argument(&mut self.current_t, self.start, self.stop, self.step)
+            .map(|t| ((self.fx)(t), (self.fy)(t)))
+    }
+}
+
+
+/// Points of the polar curve `r(theta)` in cartesian coordinates, for
+/// `theta` (in radians) going from `start` to `stop` by `step`.
+#[derive(Clone, Copy)]
+pub struct Polar<F = TypeFn>
+    where F: Fn(f64) -> f64
+{
+    pub current_theta: f64,
+    pub stop: f64,
+    pub start: f64,
+    pub step: f64,
+    pub r: F,
+}
+
+impl<F: Fn(f64) -> f64> Polar<F> {
+    pub fn new(start: f64, stop: f64, step: f64, r: F) -> Self {
+        Polar {
+            current_theta: start,
+            stop,
+            start,
+            step,
+            r,
+        }
     }
 }
 
+impl<F: Fn(f64) -> f64> Iterator for Polar<F> {
+    type Item = (f64, f64);
 
+    #[inline]
+    fn next(&mut self) -> Option<(f64, f64)> {
+        next_argument(&mut self.current_theta, self.start, self.stop, self.step).map(|theta| {
+            let r = (self.r)(theta);
+            (r * theta.cos(), r * theta.sin())
+        })
+    }
+}
+
+
+// argument at `current`, moving `current` one step further
+#[inline]
+fn next_argument(current: &mut f64, start: f64, stop: f64, step: f64) -> Option<f64> {
+    if start < stop {
+        if *current < stop {
+            let x = *current;
+            *current += step;
+            Some(x)
+        } else if (stop - *current).abs() < EPSILON {
+            *current += step;
+            Some(stop)
+        } else {
+            None
+        }
+    } else if *current > stop {
+        let x = *current;
+        *current -= step;
+        Some(x)
+    } else if (stop - *current).abs() < EPSILON {
+        *current -= step;
+        Some(stop)
+    } else {
+        None
+    }
+}
+
+/// `formula!(y(x) = <expr>, x = [start, stop; step])` samples `<expr>`
+/// like `Formula` does, all such formulas having the same type.
+///
+/// Values the expression needs from the environment are bound after the
+/// range, `formula!(y(x) = k * x, x = [0, 1; 0.1], k = coefficient)`. The
+/// formula then holds a closure, so its type is unique.
 #[macro_export]
 macro_rules! formula {
     ( y($x:ident) = $form:expr, x = [$start:expr, $stop:expr; $step:expr] )
@@ -74,16 +160,144 @@ macro_rules! formula {
         let start = $start as f64;
         let stop = $stop as f64;
         let step = $step as f64;
-        Formula::new(start, stop, step, f)
+        $crate::Formula::new(start, stop, step, f as $crate::macros::TypeFn)
+    }};
+    ( y($x:ident) = $form:expr, x = [$start:expr, $stop:expr; $step:expr]
+      $(, $name:ident = $value:expr)+ )
+    =>
+    {
+        {
+        $(let $name = $value;)+
+        let start = $start as f64;
+        let stop = $stop as f64;
+        let step = $step as f64;
+        $crate::Formula::new(start, stop, step, move |$x: f64| -> f64 { $form })
     }};
 }
 
 
 
 
+/// `parametric!(x(t) = <expr>, y(t) = <expr>, t = [start, stop; step])`
+/// samples a parametric curve like `Parametric` does. Values from the
+/// environment are bound after the range, as with `formula!`.
+#[macro_export]
+macro_rules! parametric {
+    ( x($tx:ident) = $form_x:expr, y($ty:ident) = $form_y:expr,
+      t = [$start:expr, $stop:expr; $step:expr] )
+    =>
+    {
+        {
+        fn fx($tx: f64) -> f64 { $form_x }
+        fn fy($ty: f64) -> f64 { $form_y }
+        let start = $start as f64;
+        let stop = $stop as f64;
+        let step = $step as f64;
+        $crate::Parametric::new(start,
+                                stop,
+                                step,
+                                fx as $crate::macros::TypeFn,
+                                fy as $crate::macros::TypeFn)
+    }};
+    ( x($tx:ident) = $form_x:expr, y($ty:ident) = $form_y:expr,
+      t = [$start:expr, $stop:expr; $step:expr]
+      $(, $name:ident = $value:expr)+ )
+    =>
+    {
+        {
+        $(let $name = $value;)+
+        let start = $start as f64;
+        let stop = $stop as f64;
+        let step = $step as f64;
+        $crate::Parametric::new(start,
+                                stop,
+                                step,
+                                move |$tx: f64| -> f64 { $form_x },
+                                move |$ty: f64| -> f64 { $form_y })
+    }};
+}
+
+
+/// `polar!(r(theta) = <expr>, theta = [start, stop; step])` samples a
+/// polar curve like `Polar` does. Values from the environment are bound
+/// after the range, as with `formula!`.
+#[macro_export]
+macro_rules! polar {
+    ( r($theta:ident) = $form:expr, theta = [$start:expr, $stop:expr; $step:expr] )
+    =>
+    {
+        {
+        #[allow(unused_variables)]
+        fn r($theta: f64) -> f64 { $form }
+        let start = $start as f64;
+        let stop = $stop as f64;
+        let step = $step as f64;
+        $crate::Polar::new(start, stop, step, r as $crate::macros::TypeFn)
+    }};
+    ( r($theta:ident) = $form:expr, theta = [$start:expr, $stop:expr; $step:expr]
+      $(, $name:ident = $value:expr)+ )
+    =>
+    {
+        {
+        $(let $name = $value;)+
+        let start = $start as f64;
+        let stop = $stop as f64;
+        let step = $step as f64;
+        #[allow(unused_variables)]
+        let r = move |$theta: f64| -> f64 { $form };
+        $crate::Polar::new(start, stop, step, r)
+    }};
+}
+
+
 #[test]
 fn macros_test() {
     let points = formula!(y(x) = x.powi(2), x = [1, 1; 0.1]);
     let pp: Vec<_> = points.collect();
     assert_eq!(pp, vec![(1f64, 1f64)]);
 }
+
+#[test]
+fn macros_capture_test() {
+    let k = 3f64;
+    let points = formula!(y(x) = w * x, x = [0, 1; 0.5], w = k);
+    let pp: Vec<_> = points.collect();
+    assert_eq!(pp, vec![(0f64, 0f64), (0.5f64, 1.5f64), (1f64, 3f64)]);
+
+    let points = formula!(y(x) = a * x + b, x = [0, 1; 1], a = k, b = 1f64);
+    let pp: Vec<_> = points.collect();
+    assert_eq!(pp, vec![(0f64, 1f64), (1f64, 4f64)]);
+}
+
+#[test]
+fn formula_from_closure_test() {
+    let offsets: Vec<f64> = (1..3).map(|i| i as f64).collect();
+    let f = Formula::new(0f64, 1f64, 1f64, move |x: f64| x + offsets.iter().sum::<f64>());
+    let pp: Vec<_> = f.clone().collect();
+    assert_eq!(pp, vec![(0f64, 3f64), (1f64, 4f64)]);
+    assert_eq!(f.count(), 2);
+}
+
+#[test]
+fn parametric_test() {
+    let points: Vec<_> = parametric!(x(t) = 2f64 * t, y(t) = t * t, t = [0, 2; 1]).collect();
+    assert_eq!(points, vec![(0f64, 0f64), (2f64, 1f64), (4f64, 4f64)]);
+
+    let points: Vec<_> = parametric!(x(t) = a * t, y(t) = t, t = [1, 0; 1], a = 3f64).collect();
+    assert_eq!(points, vec![(3f64, 1f64), (0f64, 0f64)]);
+}
+
+#[test]
+fn polar_test() {
+    let circle: Vec<_> = polar!(r(theta) = 2f64, theta = [0, 6.3; 0.1]).collect();
+    assert!(circle.iter().all(|p| ((p.0 * p.0 + p.1 * p.1).sqrt() - 2f64).abs() < EPSILON));
+    assert_eq!(circle[0], (2f64, 0f64));
+
+    let pitch = 0.5f64;
+    let spiral: Vec<_> = polar!(r(theta) = k * theta,
+                                theta = [0, ::std::f64::consts::PI; 0.01],
+                                k = pitch)
+        .collect();
+    let last = spiral.last().unwrap();
+    assert!((last.0 + pitch * ::std::f64::consts::PI).abs() < 0.01 && last.1.abs() < 0.01);
+}
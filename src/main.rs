@@ -1,30 +1,640 @@
-#[macro_use]
 extern crate simple_chart;
-use simple_chart::{Chart, Serie, Axis, Formula};
-use std::io::prelude::*;
+
+use simple_chart::chart::GraphError;
+use simple_chart::{AdaptiveSampler, AnsiColors, AxisSpec, BitMap, Braille, Canvas, Chart, ChartSpec, Color, Column,
+                   Compression, CsvReader, Expression, Html, NetpbmEncoding, Point, Serie, PALETTE, read_pairs,
+                   write_eps, write_kitty, write_pdf, write_sixel};
+use std::env;
 use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Write};
+use std::process;
+use std::vec;
+
+const USAGE: &str = "\
+Usage: simple-chart [OPTIONS] [FILE...]
+
+Draws series read from CSV files and formulas of x. A FILE named - is the
+standard input, which is also read when neither FILE nor --expr is given.
+
+The standard input holds CSV data or `x y` pairs separated by whitespace or
+commas, one per line; blank lines separate series of pairs and `#` starts a
+comment. Pairs are assumed when the first line that is not blank or a comment
+splits on whitespace but holds no delimiter.
+
+Input:
+    --stdin FORMAT          auto, csv or pairs [default: auto]
+    --expr EXPR             formula of x, like 'sin(x) / x', may repeat
+    --expr-range MIN:MAX    x range of formulas [default: the x axis range or -10:10]
+    --delimiter CHAR        CSV field separator [default: ,]
+    --no-header             the first CSV row holds values, not column names
+    --x-column COLUMN       name or index of the x column [default: 0]
+    --y-column COLUMN       name or index of a y column, may repeat
+                            [default: every column but x]
+    --color COLOR           color of the next --y-column or --expr serie, may repeat
+
+Chart:
+    --width N               [default: 740]
+    --height N              [default: 480]
+    --background COLOR      [default: #ffffff]
+    --axis-color COLOR      [default: #000000]
+    --title TEXT
+    --x-title TEXT
+    --y-title TEXT
+    --x-range MIN:MAX       x axis range [default: calculated from series]
+    --y-range MIN:MAX       y axis range [default: calculated from series]
+    --x-intervals N         intervals on the x axis with --x-range [default: 5]
+    --y-intervals N         intervals on the y axis with --y-range [default: 5]
+    --x-decimals N          decimal places of x axis labels with --x-range [default: 1]
+    --y-decimals N          decimal places of y axis labels with --y-range [default: 1]
+    --polar                 points are (theta, r), theta in radians
+
+Output:
+    -o, --output PATH       output file, - for the standard output
+                            [default: graph.bmp, graph.pnm for netpbm, graph.html,
+                            graph.eps and graph.pdf for those, - for braille,
+                            sixel and kitty]
+    -f, --format FORMAT     bmp, netpbm, html, eps, pdf, braille, sixel or kitty
+                            [default: bmp]
+    --rle                   run length encoded bmp, much smaller
+    --plain                 netpbm values as text rather than bytes
+    -t, --terminal          same as --format braille, draws in the terminal
+    --columns N             width of braille text [default: $COLUMNS or 80]
+    --rows N                height of braille text [default: $LINES or 24]
+    --colors MODE           colors of braille text: none, 256 or truecolor
+                            [default: truecolor if $COLORTERM tells so, else 256]
+    -h, --help              prints this message
+";
+
+const DEFAULT_EXPR_RANGE: (f64, f64) = (-10f64, 10f64);
+const DEFAULT_INTERVALS: u8 = 5;
+const DEFAULT_DECIMALS: u8 = 1;
+
+type Points = Serie<vec::IntoIter<Point>, Point>;
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Bmp,
+    Netpbm,
+    Html,
+    Eps,
+    Pdf,
+    Braille,
+    Sixel,
+    Kitty,
+}
+
+// chart drawn before the output is opened, so that errors leave no empty file
+// behind
+enum Rendered {
+    Canvas(Canvas),
+    Bytes(Vec<u8>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StdinFormat {
+    Auto,
+    Pairs,
+    Csv,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct AxisOptions {
+    range: Option<(f64, f64)>,
+    intervals: Option<u8>,
+    decimals: Option<u8>,
+}
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    help: bool,
+    inputs: Vec<String>,
+    stdin_format: StdinFormat,
+    expressions: Vec<(String, Option<String>)>,
+    expr_range: Option<(f64, f64)>,
+    delimiter: char,
+    header: bool,
+    x_column: Column,
+    y_columns: Vec<(Column, Option<String>)>,
+    next_color: Option<String>,
+    width: usize,
+    height: usize,
+    background_color: String,
+    axis_color: String,
+    title: Option<String>,
+    x_title: Option<String>,
+    y_title: Option<String>,
+    axis_x: AxisOptions,
+    axis_y: AxisOptions,
+    polar: bool,
+    output: Option<String>,
+    format: Format,
+    rle: bool,
+    plain: bool,
+    columns: Option<usize>,
+    rows: Option<usize>,
+    colors: Option<AnsiColors>,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            help: false,
+            inputs: vec![],
+            stdin_format: StdinFormat::Auto,
+            expressions: vec![],
+            expr_range: None,
+            delimiter: ',',
+            header: true,
+            x_column: Column::Index(0),
+            y_columns: vec![],
+            next_color: None,
+            width: 740,
+            height: 480,
+            background_color: "#ffffff".to_string(),
+            axis_color: "#000000".to_string(),
+            title: None,
+            x_title: None,
+            y_title: None,
+            axis_x: AxisOptions::default(),
+            axis_y: AxisOptions::default(),
+            polar: false,
+            output: None,
+            format: Format::Bmp,
+            rle: false,
+            plain: false,
+            columns: None,
+            rows: None,
+            colors: None,
+        }
+    }
+}
 
 
 fn main() {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("simple-chart: {}\n\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+    if options.help {
+        print!("{}", USAGE);
+        return;
+    }
+    let stdin = io::stdin();
+    if let Err(err) = run(&options, stdin.lock()) {
+        eprintln!("simple-chart: {}", err);
+        process::exit(1);
+    }
+}
+
+fn run<R: BufRead>(options: &Options, mut stdin: R) -> Result<(), GraphError> {
+    let chart = create_chart(options)?;
+
+    let mut series: Vec<Points> = vec![];
+    let read_stdin = options.inputs.is_empty() && options.expressions.is_empty();
+    if read_stdin || !options.inputs.is_empty() {
+        let reader = csv_reader(options)?;
+        if read_stdin {
+            series.extend(read_stdin_series(options, &reader, &mut stdin)?);
+        }
+        for input in &options.inputs {
+            if input == "-" {
+                series.extend(read_stdin_series(options, &reader, &mut stdin)?);
+            } else {
+                series.extend(reader.read_path(input)?);
+            }
+        }
+    }
+
+    // palette colors of formulas follow those of the read series
+    let read = series.len();
+    for (i, (source, color)) in options.expressions.iter().enumerate() {
+        let color = serie_color(color, read + i)?;
+        series.extend(expression_series(&chart, options, source, &color)?);
+    }
+    // nothing to fit an unset axis to
+    if series.is_empty() && (options.axis_x.range.is_none() || options.axis_y.range.is_none()) {
+        return Err(GraphError::NotEnoughPoints);
+    }
+
+    let default_output = match options.format {
+        Format::Bmp => "graph.bmp",
+        Format::Netpbm => "graph.pnm",
+        Format::Html => "graph.html",
+        Format::Eps => "graph.eps",
+        Format::Pdf => "graph.pdf",
+        _ => "-",
+    };
+    // vector formats and text come whole, pictures are encoded into the output
+    let rendered = match options.format {
+        Format::Html => Rendered::Bytes(Html::new().render(&chart, series.into_iter())?.into_bytes()),
+        Format::Eps | Format::Pdf => {
+            let mut bytes = vec![];
+            if options.format == Format::Eps {
+                write_eps(&chart, series.into_iter(), &mut bytes)?
+            } else {
+                write_pdf(&chart, series.into_iter(), &mut bytes)?
+            }
+            Rendered::Bytes(bytes)
+        }
+        Format::Braille => {
+            let braille = Braille::new(terminal_size(options.columns, "COLUMNS", 80),
+                                       terminal_size(options.rows, "LINES", 24))
+                .colors(options.colors.unwrap_or_else(terminal_colors));
+            Rendered::Bytes(braille.render(&chart, series.into_iter())?.into_bytes())
+        }
+        _ => Rendered::Canvas(chart.render(series.into_iter())),
+    };
+
+    let mut output = open_output(options.output.as_ref().map_or(default_output, |o| &**o))?;
+    match rendered {
+        Rendered::Bytes(bytes) => output.write_all(&bytes)?,
+        Rendered::Canvas(canvas) => {
+            match options.format {
+                Format::Bmp if options.rle => {
+                    let mut bitmap = BitMap::from(&canvas);
+                    bitmap.set_compression(Compression::Rle);
+                    bitmap.write_to(&mut output)?
+                }
+                Format::Netpbm => {
+                    let encoding = if options.plain {
+                        NetpbmEncoding::Plain
+                    } else {
+                        NetpbmEncoding::Binary
+                    };
+                    BitMap::from(&canvas).write_netpbm(&mut output, encoding)?
+                }
+                Format::Sixel => write_sixel(&canvas, &mut output)?,
+                Format::Kitty => write_kitty(&canvas, &mut output)?,
+                _ => canvas.write_to(&mut output)?,
+            }
+        }
+    }
+    output.flush()?;
+    Ok(())
+}
+
+fn terminal_size(size: Option<usize>, variable: &str, default: usize) -> usize {
+    size.or_else(|| env::var(variable).ok().and_then(|v| v.parse().ok()))
+        .unwrap_or(default)
+}
+
+fn terminal_colors() -> AnsiColors {
+    match env::var("COLORTERM") {
+        Ok(ref value) if value == "truecolor" || value == "24bit" => AnsiColors::TrueColor,
+        _ => AnsiColors::Palette256,
+    }
+}
+
+fn create_chart(options: &Options) -> Result<Chart, GraphError> {
+    let spec = ChartSpec {
+        width: options.width,
+        height: options.height,
+        background_color: options.background_color.clone(),
+        axis_color: options.axis_color.clone(),
+        title: options.title.clone(),
+        axis_x_title: options.x_title.clone(),
+        axis_y_title: options.y_title.clone(),
+        polar: options.polar,
+        axis_x: axis_spec(&options.axis_x),
+        axis_y: axis_spec(&options.axis_y),
+        series: vec![],
+    };
+    spec.chart()
+}
+
+fn axis_spec(options: &AxisOptions) -> Option<AxisSpec> {
+    options.range.map(|(min, max)| {
+        AxisSpec {
+            min,
+            max,
+            interval_count: options.intervals.unwrap_or(DEFAULT_INTERVALS),
+            decimal_places: options.decimals.unwrap_or(DEFAULT_DECIMALS),
+        }
+    })
+}
+
+fn csv_reader(options: &Options) -> Result<CsvReader, GraphError> {
+    let mut reader = CsvReader::new()
+        .delimiter(options.delimiter)
+        .has_header(options.header)
+        .x_column(options.x_column.clone());
+    for (i, (column, color)) in options.y_columns.iter().enumerate() {
+        reader = reader.y_column(column.clone(), &serie_color(color, i)?);
+    }
+    Ok(reader)
+}
+
+fn read_stdin_series<R: BufRead>(options: &Options,
+                                 reader: &CsvReader,
+                                 mut stdin: R)
+                                 -> Result<Vec<Points>, GraphError> {
+    match options.stdin_format {
+        StdinFormat::Pairs => read_pairs(stdin),
+        StdinFormat::Csv => reader.read(stdin),
+        StdinFormat::Auto => {
+            let mut input = vec![];
+            stdin.read_to_end(&mut input)?;
+            if holds_pairs(&String::from_utf8_lossy(&input), options.delimiter) {
+                read_pairs(&input[..])
+            } else {
+                reader.read(&input[..])
+            }
+        }
+    }
+}
+
+// the first line that is not blank or a comment is split by whitespace, not
+// by the delimiter, as in `seq 1 9 | awk '{print $1, $1*$1}'`
+fn holds_pairs(input: &str, delimiter: char) -> bool {
+    input.lines()
+        .map(|line| line.trim())
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .is_none_or(|line| !line.contains(delimiter) && line.split_whitespace().count() > 1)
+}
+
+fn serie_color(color: &Option<String>, index: usize) -> Result<String, GraphError> {
+    match *color {
+        Some(ref color) => color.parse::<Color>().map(|_| color.clone()),
+        None => Ok(PALETTE[index % PALETTE.len()].to_string()),
+    }
+}
+
+// continuous pieces of the formula line, the first one named after it
+fn expression_series(chart: &Chart,
+                     options: &Options,
+                     source: &str,
+                     color: &str)
+                     -> Result<Vec<Points>, GraphError> {
+    let expression = Expression::parse(source)?;
+    let (start, stop) = options.expr_range
+        .or(options.axis_x.range)
+        .unwrap_or(DEFAULT_EXPR_RANGE);
+
+    let series = AdaptiveSampler::for_chart(chart, start, stop)
+        .series(|x| expression.eval(x), color)
+        .iter()
+        .map(|serie| serie.collect_points())
+        .collect::<Vec<Points>>();
+    if series.is_empty() {
+        return Err(GraphError::NotEnoughPoints);
+    }
+    Ok(series.into_iter()
+        .enumerate()
+        .map(|(i, serie)| if i == 0 { serie.add_name(source) } else { serie })
+        .collect())
+}
+
+// - is the standard output
+fn open_output(output: &str) -> io::Result<Box<dyn Write>> {
+    if output == "-" {
+        Ok(Box::new(io::stdout()))
+    } else {
+        Ok(Box::new(BufWriter::new(File::create(output)?)))
+    }
+}
+
+
+fn parse_args<I: Iterator<Item = String>>(args: I) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = args.peekable();
+
+    while let Some(arg) = args.next() {
+        if arg == "-" || !arg.starts_with('-') {
+            options.inputs.push(arg);
+            continue;
+        }
+
+        // `--name=value` as well as `--name value`
+        let (name, inline) = match arg.find('=') {
+            Some(i) if arg.starts_with("--") => (arg[..i].to_string(), Some(arg[i + 1..].to_string())),
+            _ => (arg.clone(), None),
+        };
+        let mut value = || -> Result<String, String> {
+            match inline.clone() {
+                Some(value) => Ok(value),
+                None => args.next().ok_or_else(|| format!("{} needs a value", name)),
+            }
+        };
+
+        match &*name {
+            "-h" | "--help" => options.help = true,
+            "--expr" => {
+                let expression = value()?;
+                let color = options.next_color.take();
+                options.expressions.push((expression, color));
+            }
+            "--stdin" => {
+                options.stdin_format = match &*value()? {
+                    "auto" => StdinFormat::Auto,
+                    "pairs" => StdinFormat::Pairs,
+                    "csv" => StdinFormat::Csv,
+                    format => return Err(format!("unknown stdin format '{}'", format)),
+                }
+            }
+            "--expr-range" => options.expr_range = Some(parse_range(&name, &value()?)?),
+            "--delimiter" => {
+                let delimiter = value()?;
+                let mut chars = delimiter.chars();
+                options.delimiter = match (chars.next(), chars.next()) {
+                    (Some(c), None) => c,
+                    _ => return Err(format!("{} takes a single character", name)),
+                };
+            }
+            "--no-header" => options.header = false,
+            "--x-column" => options.x_column = parse_column(&value()?),
+            "--y-column" => {
+                let column = parse_column(&value()?);
+                let color = options.next_color.take();
+                options.y_columns.push((column, color));
+            }
+            "--color" => options.next_color = Some(value()?),
+            "--width" => options.width = parse_number(&name, &value()?)?,
+            "--height" => options.height = parse_number(&name, &value()?)?,
+            "--background" => options.background_color = value()?,
+            "--axis-color" => options.axis_color = value()?,
+            "--title" => options.title = Some(value()?),
+            "--x-title" => options.x_title = Some(value()?),
+            "--y-title" => options.y_title = Some(value()?),
+            "--x-range" => options.axis_x.range = Some(parse_range(&name, &value()?)?),
+            "--y-range" => options.axis_y.range = Some(parse_range(&name, &value()?)?),
+            "--x-intervals" => options.axis_x.intervals = Some(parse_number(&name, &value()?)?),
+            "--y-intervals" => options.axis_y.intervals = Some(parse_number(&name, &value()?)?),
+            "--x-decimals" => options.axis_x.decimals = Some(parse_number(&name, &value()?)?),
+            "--y-decimals" => options.axis_y.decimals = Some(parse_number(&name, &value()?)?),
+            "--polar" => options.polar = true,
+            "-o" | "--output" => options.output = Some(value()?),
+            "-f" | "--format" => {
+                options.format = match &*value()? {
+                    "bmp" => Format::Bmp,
+                    "netpbm" => Format::Netpbm,
+                    "html" => Format::Html,
+                    "eps" => Format::Eps,
+                    "pdf" => Format::Pdf,
+                    "braille" => Format::Braille,
+                    "sixel" => Format::Sixel,
+                    "kitty" => Format::Kitty,
+                    format => return Err(format!("unknown format '{}'", format)),
+                }
+            }
+            "--rle" => options.rle = true,
+            "--plain" => options.plain = true,
+            "-t" | "--terminal" => options.format = Format::Braille,
+            "--columns" => options.columns = Some(parse_number(&name, &value()?)?),
+            "--rows" => options.rows = Some(parse_number(&name, &value()?)?),
+            "--colors" => {
+                options.colors = match &*value()? {
+                    "none" => Some(AnsiColors::None),
+                    "256" => Some(AnsiColors::Palette256),
+                    "truecolor" => Some(AnsiColors::TrueColor),
+                    mode => return Err(format!("unknown colors mode '{}'", mode)),
+                }
+            }
+            _ => return Err(format!("unknown option {}", name)),
+        }
+    }
+
+    if options.next_color.is_some() {
+        return Err("--color must come before the --y-column or --expr it colors".to_string());
+    }
+    if options.rle && options.format != Format::Bmp {
+        return Err("--rle needs the bmp format".to_string());
+    }
+    for &(axis, axis_options) in &[("x", &options.axis_x), ("y", &options.axis_y)] {
+        if axis_options.range.is_none() &&
+           (axis_options.intervals.is_some() || axis_options.decimals.is_some()) {
+            return Err(format!("--{0}-intervals and --{0}-decimals need --{0}-range", axis));
+        }
+    }
+    Ok(options)
+}
+
+fn parse_number<N: std::str::FromStr>(name: &str, value: &str) -> Result<N, String> {
+    value.parse().map_err(|_| format!("{} takes a whole number, not '{}'", name, value))
+}
+
+fn parse_range(name: &str, value: &str) -> Result<(f64, f64), String> {
+    let error = || format!("{} takes MIN:MAX, not '{}'", name, value);
+    let mut parts = value.splitn(2, ':');
+    let min = parts.next().and_then(|s| s.parse::<f64>().ok()).ok_or_else(&error)?;
+    let max = parts.next().and_then(|s| s.parse::<f64>().ok()).ok_or_else(&error)?;
+    if min < max {
+        Ok((min, max))
+    } else {
+        Err(format!("{} needs MIN less than MAX, not '{}'", name, value))
+    }
+}
+
+fn parse_column(value: &str) -> Column {
+    match value.parse::<usize>() {
+        Ok(index) => Column::Index(index),
+        Err(_) => Column::Name(value.to_string()),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        parse_args(args.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn parse_args_test() {
+        let options = parse(&["data.csv",
+                              "--width=300",
+                              "--x-range",
+                              "-1:1",
+                              "--x-intervals",
+                              "4",
+                              "--color",
+                              "#ff0000",
+                              "--expr",
+                              "x^2",
+                              "--y-column",
+                              "temp",
+                              "-o",
+                              "-"])
+            .unwrap();
+        assert_eq!(options.inputs, vec!["data.csv".to_string()]);
+        assert_eq!(options.width, 300);
+        assert_eq!(options.axis_x.range, Some((-1f64, 1f64)));
+        assert_eq!(options.axis_x.intervals, Some(4));
+        assert_eq!(options.expressions,
+                   vec![("x^2".to_string(), Some("#ff0000".to_string()))]);
+        assert_eq!(options.y_columns, vec![(Column::Name("temp".to_string()), None)]);
+        assert_eq!(options.output, Some("-".to_string()));
+        assert_eq!(options.stdin_format, StdinFormat::Auto);
+        assert_eq!(parse(&["--stdin", "pairs"]).unwrap().stdin_format, StdinFormat::Pairs);
+    }
+
+    #[test]
+    fn bad_args_test() {
+        assert_eq!(parse(&["--width", "wide"]).err().unwrap(),
+                   "--width takes a whole number, not 'wide'");
+        assert_eq!(parse(&["--x-range", "1:0"]).err().unwrap(),
+                   "--x-range needs MIN less than MAX, not '1:0'");
+        assert_eq!(parse(&["--y-decimals", "2"]).err().unwrap(),
+                   "--y-intervals and --y-decimals need --y-range");
+        assert_eq!(parse(&["--rle", "--format", "netpbm"]).err().unwrap(), "--rle needs the bmp format");
+        assert_eq!(parse(&["--title"]).err().unwrap(), "--title needs a value");
+        assert_eq!(parse(&["--colour", "#ff0000"]).err().unwrap(), "unknown option --colour");
+    }
+
+    #[test]
+    fn graph_errors_are_reported_test() {
+        let mut options = parse(&["--expr", "sin(x"]).unwrap();
+        assert_eq!(run(&options, io::empty()).err().unwrap().to_string(),
+                   "Invalid expression at position 4: unclosed '('");
 
-    let mut chart = Chart::new(740, 740, "#000000", "#ffffff")
-        .unwrap()
-        .add_axis_x(Axis::new(-1.0, 1.0, 10, 2));
+        options = parse(&["--expr", "x", "--background", "white"]).unwrap();
+        assert_eq!(run(&options, io::empty()).err().unwrap().to_string(),
+                   "Invalid chart specification: background_color: 'white' is not a color like \
+                    #rrggbb");
 
-    let f1 = formula!(y(x) = (1f64 - x.powi(2)).abs().sqrt(), x = [-1f64, 1f64; 0.01]);
-    let f2 = formula!(y(x) = -(1f64 - x.powi(2)).abs().sqrt(), x = [1f64, -1f64; 0.01]);
-    let ff1 = f1.chain(f2);
+        let path = env::temp_dir().join("simple-chart-no-output.txt");
+        let _ = std::fs::remove_file(&path);
+        options = parse(&["--expr", "x", "--format", "braille", "--columns", "3",
+                          "-o", path.to_str().unwrap()]).unwrap();
+        assert_eq!(run(&options, io::empty()).err().unwrap().to_string(),
+                   "There are not enough width and height to form graph with axis.");
+        assert!(!path.exists());
+    }
 
-    let f3 = formula!(y(x) = (0.64f64 - x.powi(2)).abs().sqrt(), x = [-0.8f64, 0.8f64; 0.01]);
-    let f4 = formula!(y(x) = -(0.64f64 - x.powi(2)).abs().sqrt(), x = [0.8f64, -0.8f64; 0.01]);
+    #[test]
+    fn stdin_format_detection_test() {
+        let squares = (1..101).map(|x| format!("{} {}\n", x, x * x)).collect::<String>();
+        assert!(holds_pairs(&squares, ','));
+        assert!(holds_pairs("# x y\n\n1\t2\n", ','));
+        assert!(!holds_pairs("x,y\n1,2\n", ','));
+        assert!(!holds_pairs("date;high temp\n1;2\n", ';'));
 
-    let ff2 = f3.chain(f4);
+        let path = env::temp_dir().join("simple-chart-squares.bmp");
+        let options = parse(&["-o", path.to_str().unwrap()]).unwrap();
+        run(&options, squares.as_bytes()).unwrap();
+        assert!(path.exists());
+        let _ = std::fs::remove_file(&path);
+    }
 
-    let serie1 = Serie::new(ff1.into_iter(), "#ff0000").unwrap();
-    let serie2 = Serie::new(ff2.into_iter(), "#00ff00").unwrap();
-    let series = vec![serie1, serie2];
-    let bmp = chart.draw(series.into_iter());
+    #[test]
+    fn empty_and_flat_inputs_test() {
+        let path = env::temp_dir().join("simple-chart-flat.bmp");
+        let options = parse(&["--expr", "1", "-o", path.to_str().unwrap()]).unwrap();
+        run(&options, io::empty()).unwrap();
+        assert!(path.exists());
+        let _ = std::fs::remove_file(&path);
 
-    let mut file = File::create("graph.bmp").unwrap();
-    file.write_all(&bmp).unwrap();
+        let options = parse(&["-o", path.to_str().unwrap()]).unwrap();
+        assert_eq!(run(&options, &b""[..]).err().unwrap().to_string(),
+                   "There are not enough points to display on graph.");
+        let options = parse(&["--stdin", "pairs", "-o", path.to_str().unwrap()]).unwrap();
+        assert_eq!(run(&options, &b"# x y\n\n# nothing yet\n"[..]).err().unwrap().to_string(),
+                   "There are not enough points to display on graph.");
+        assert!(!path.exists());
+    }
 }
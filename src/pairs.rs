@@ -0,0 +1,119 @@
+use std::f64;
+use std::io::BufRead;
+use std::vec;
+
+use Point;
+use Serie;
+use chart::{GraphError, PALETTE};
+
+
+/// Reads series from lines of `x y` pairs, gnuplot style, until the end of
+/// `reader`. Values are separated by whitespace or commas, blank lines end
+/// a serie and everything after `#` is a comment. A line with a single
+/// value is `y`, its `x` is the index of the point in the serie. Empty
+/// values between commas and `NA` are missing, the line breaks there.
+///
+/// Series get the colors of `PALETTE` in turn.
+pub fn read_pairs<R: BufRead>(reader: R) -> Result<Vec<Serie<vec::IntoIter<Point>, Point>>, GraphError> {
+    let mut blocks: Vec<Vec<Point>> = vec![];
+    let mut current: Vec<Point> = vec![];
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let data = match line.find('#') {
+            Some(comment) => &line[..comment],
+            None => &line[..],
+        };
+        // commas keep empty values, which are missing
+        let values = if data.contains(',') {
+            data.split(',').map(str::trim).collect::<Vec<&str>>()
+        } else {
+            data.split_whitespace().collect::<Vec<&str>>()
+        };
+
+        if values.iter().all(|s| s.is_empty()) {
+            // comment lines do not end a serie
+            if line.trim().is_empty() && !current.is_empty() {
+                blocks.push(::std::mem::take(&mut current));
+            }
+            continue;
+        }
+
+        let numbers = values.iter()
+            .map(|&s| {
+                if s.is_empty() || s == "NA" {
+                    return Ok(f64::NAN);
+                }
+                s.parse::<f64>()
+                    .map_err(|_| GraphError::MalformedRow(i + 1, format!("'{}' is not a number", s)))
+            })
+            .collect::<Result<Vec<f64>, GraphError>>()?;
+        let point = match numbers.len() {
+            1 => Point {
+                x: current.len() as f64,
+                y: numbers[0],
+            },
+            2 => Point {
+                x: numbers[0],
+                y: numbers[1],
+            },
+            count => {
+                return Err(GraphError::MalformedRow(i + 1,
+                                                    format!("expected x and y, found {} values",
+                                                            count)))
+            }
+        };
+        current.push(point);
+    }
+    if !current.is_empty() {
+        blocks.push(current);
+    }
+
+    blocks.into_iter()
+        .enumerate()
+        .map(|(k, points)| Serie::new(points.into_iter(), PALETTE[k % PALETTE.len()]))
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(text: &str) -> Vec<Vec<(f64, f64)>> {
+        read_pairs(text.as_bytes())
+            .unwrap()
+            .iter()
+            .map(|s| s.iter.clone().map(|p| (p.x, p.y)).collect())
+            .collect()
+    }
+
+    #[test]
+    fn blocks_and_comments_test() {
+        let text = "# squares\n1 1\n2\t4  # last\n\n\n# cubes\n1,1\n2, 8\n# end\n";
+        assert_eq!(points(text),
+                   vec![vec![(1f64, 1f64), (2f64, 4f64)], vec![(1f64, 1f64), (2f64, 8f64)]]);
+    }
+
+    #[test]
+    fn single_values_test() {
+        assert_eq!(points("5\n7\n6"), vec![vec![(0f64, 5f64), (1f64, 7f64), (2f64, 6f64)]]);
+    }
+
+    #[test]
+    fn missing_values_test() {
+        let series = read_pairs("1 1\n2 NA\n3,\n4, 4\n5 5\n".as_bytes()).unwrap();
+        let points = series[0].iter.clone().collect::<Vec<Point>>();
+        assert_eq!(points.len(), 5);
+        assert_eq!((points[2].x, points[3].y), (3f64, 4f64));
+        assert!(points[1].y.is_nan() && points[2].y.is_nan());
+    }
+
+    #[test]
+    fn malformed_line_test() {
+        let error = read_pairs("1 2\n3 4 5\n".as_bytes()).err().unwrap();
+        assert_eq!(error.to_string(), "Malformed row at line 2: expected x and y, found 3 values");
+        let error = read_pairs("1 2\n3 y\n".as_bytes()).err().unwrap();
+        assert_eq!(error.to_string(), "Malformed row at line 2: 'y' is not a number");
+    }
+}
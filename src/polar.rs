@@ -0,0 +1,258 @@
+use std::f64::consts::PI;
+
+use Axis;
+use DisplayPoint;
+use Point;
+use font::{self, H_CHAR};
+use layout::{Rect, W_GAP};
+use line;
+
+const SPOKES: usize = 12;     //angular grid lines, one every 30 degrees
+const W_ANGLE_LABEL: usize = 14;     //width of the widest angle label, "330"
+
+
+/// Polar grid centered in a plot: concentric rings for radius ticks and
+/// spokes labelled with their angle in degrees. Points are `(theta, r)`,
+/// `theta` being in radians counted counterclockwise from the right.
+#[derive(Debug, Clone)]
+pub struct PolarPlot {
+    center_x: usize,
+    center_y: usize,
+    radius: usize,
+    axis_r: Axis,
+}
+
+impl PolarPlot {
+    /// Radius in pixels of the largest grid circle fitting `plot` with its
+    /// angle labels around.
+    pub fn radius_in(plot: &Rect) -> usize {
+        let side = if plot.width < plot.height {
+            plot.width
+        } else {
+            plot.height
+        };
+        (side / 2).saturating_sub(W_ANGLE_LABEL + W_GAP)
+    }
+
+    /// `axis_r` should be fitted to `radius_in(plot)` pixels.
+    pub fn new(plot: &Rect, axis_r: Axis) -> PolarPlot {
+        PolarPlot {
+            center_x: plot.x + plot.width / 2,
+            center_y: plot.y + plot.height / 2,
+            radius: PolarPlot::radius_in(plot),
+            axis_r,
+        }
+    }
+
+    /// Grid rings, spokes and their labels.
+    pub fn create_points(&self) -> Vec<DisplayPoint> {
+        let mut v = self.create_circle(self.radius as f64);
+
+        let rings = self.axis_r.interval_count as usize;
+        for (i, label) in self.axis_r.labels().iter().enumerate() {
+            let r = self.ring_radius(i);
+            if i > 0 && i < rings {
+                v.extend(self.create_circle(r).into_iter().step_by(2));
+            }
+            v.extend(font::create_text(label,
+                                       self.center_x + r.round() as usize + W_GAP,
+                                       self.center_y + W_GAP));
+        }
+
+        for k in 0..SPOKES {
+            let angle = 2f64 * PI * (k as f64) / (SPOKES as f64);
+            let end = self.place(angle, self.radius as f64);
+            let center = DisplayPoint {
+                x: self.center_x,
+                y: self.center_y,
+            };
+            if let Some(end) = end {
+                v.extend(line::Line::new(center, end).step_by(2));
+            }
+            v.extend(self.create_angle_label(angle, &(k * 360 / SPOKES).to_string()));
+        }
+        v
+    }
+
+    /// Pixels of a point, `None` if it lies outside of the grid circle.
+    pub fn to_display(&self, p: Point) -> Option<DisplayPoint> {
+        let (min, max) = (self.axis_r.min_value, self.axis_r.max_value);
+        let rho = (p.y - min) / (max - min) * (self.radius as f64);
+        let rho = if rho < 0f64 { 0f64 } else { rho };
+        if rho > self.radius as f64 + 0.5 {
+            None
+        } else {
+            self.place(p.x, rho)
+        }
+    }
+
+    /// Pixels of the line through `points`, each of its segments clipped to
+    /// the grid circle. A single point is drawn when it lies inside.
+    pub fn polyline<I: Iterator<Item = Point>>(&self, points: I) -> Vec<DisplayPoint> {
+        let pixel = |(x, y): (f64, f64)| {
+            DisplayPoint {
+                x: (self.center_x as f64 + x).round() as usize,
+                y: (self.center_y as f64 + y).round() as usize,
+            }
+        };
+        let points = points.map(|p| self.offset(p)).collect::<Vec<(f64, f64)>>();
+        if points.len() == 1 {
+            return self.clip(points[0], points[0]).map(|(a, _)| pixel(a)).into_iter().collect();
+        }
+        let mut pixels = vec![];
+        for pair in points.windows(2) {
+            if let Some((a, b)) = self.clip(pair[0], pair[1]) {
+                let (a, b) = (pixel(a), pixel(b));
+                pixels.push(a);
+                pixels.extend(line::Line::new(a, b));
+                pixels.push(b);
+            }
+        }
+        pixels
+    }
+
+    /// `(theta, r)` shown at pixel `p`, `theta` between 0 and 2π, `None`
+    /// out of the grid circle.
+    pub fn to_data(&self, p: DisplayPoint) -> Option<Point> {
+        if !self.contains(p) {
+            return None;
+        }
+        let dx = p.x as f64 - self.center_x as f64;
+        let dy = p.y as f64 - self.center_y as f64;
+        let theta = dy.atan2(dx);
+        let theta = if theta < 0f64 { theta + 2f64 * PI } else { theta };
+        let (min, max) = (self.axis_r.min_value, self.axis_r.max_value);
+        let r = min + dx.hypot(dy) / (self.radius as f64) * (max - min);
+        Some(Point { x: theta, y: r })
+    }
+
+    /// Whether `p` lies inside of the grid circle.
+    pub fn contains(&self, p: DisplayPoint) -> bool {
+        let dx = p.x as f64 - self.center_x as f64;
+        let dy = p.y as f64 - self.center_y as f64;
+        dx.hypot(dy) <= self.radius as f64 + 0.5
+    }
+
+    // position of a point relative to the center, in pixels
+    fn offset(&self, p: Point) -> (f64, f64) {
+        let (min, max) = (self.axis_r.min_value, self.axis_r.max_value);
+        let rho = (p.y - min) / (max - min) * (self.radius as f64);
+        let rho = if rho < 0f64 { 0f64 } else { rho };
+        (rho * p.x.cos(), rho * p.x.sin())
+    }
+
+    // the part of the segment inside of the grid circle, where
+    // |from + t * (to - from)| = radius for t in [t0, t1]
+    fn clip(&self, from: (f64, f64), to: (f64, f64)) -> Option<((f64, f64), (f64, f64))> {
+        let radius = self.radius as f64 + 0.5;
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let a = dx * dx + dy * dy;
+        let b = 2f64 * (from.0 * dx + from.1 * dy);
+        let c = from.0 * from.0 + from.1 * from.1 - radius * radius;
+        if a == 0f64 {
+            return if c <= 0f64 { Some((from, to)) } else { None };
+        }
+        let discriminant = b * b - 4f64 * a * c;
+        if discriminant < 0f64 {
+            return None;
+        }
+        let t0 = ((-b - discriminant.sqrt()) / (2f64 * a)).max(0f64);
+        let t1 = ((-b + discriminant.sqrt()) / (2f64 * a)).min(1f64);
+        if t0 > t1 {
+            return None;
+        }
+        Some(((from.0 + t0 * dx, from.1 + t0 * dy), (from.0 + t1 * dx, from.1 + t1 * dy)))
+    }
+
+    fn ring_radius(&self, i: usize) -> f64 {
+        (self.radius as f64) * (i as f64) / (self.axis_r.interval_count as f64)
+    }
+
+    fn place(&self, angle: f64, rho: f64) -> Option<DisplayPoint> {
+        let x = (self.center_x as f64 + rho * angle.cos()).round();
+        let y = (self.center_y as f64 + rho * angle.sin()).round();
+        if x < 0f64 || y < 0f64 {
+            None
+        } else {
+            Some(DisplayPoint {
+                x: x as usize,
+                y: y as usize,
+            })
+        }
+    }
+
+    fn create_circle(&self, rho: f64) -> Vec<DisplayPoint> {
+        let count = (4f64 * PI * rho).ceil() as usize + 1;
+        let mut v: Vec<DisplayPoint> = vec![];
+        for i in 0..count {
+            let angle = 2f64 * PI * (i as f64) / (count as f64);
+            if let Some(p) = self.place(angle, rho) {
+                if v.last() != Some(&p) {
+                    v.push(p);
+                }
+            }
+        }
+        v
+    }
+
+    // label centered on the spoke line, just outside of the grid circle
+    fn create_angle_label(&self, angle: f64, label: &str) -> Vec<DisplayPoint> {
+        let width = font::text_width(label) as f64;
+        let height = H_CHAR as f64;
+        let rho = (self.radius + W_GAP) as f64;
+        let x = self.center_x as f64 + rho * angle.cos() + (angle.cos() - 1f64) * width / 2f64;
+        let y = self.center_y as f64 + rho * angle.sin() + (angle.sin() - 1f64) * height / 2f64;
+        if x < 0f64 || y < 0f64 {
+            vec![]
+        } else {
+            font::create_text(label, x.round() as usize, y.round() as usize)
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+    use super::*;
+
+    fn plot() -> PolarPlot {
+        let rect = Rect::new(10, 10, 200, 100);
+        let axis = Axis::set_axis_manual(0f64, 10f64, 5, 0, PolarPlot::radius_in(&rect));
+        PolarPlot::new(&rect, axis)
+    }
+
+    #[test]
+    fn radius_fits_smaller_side_test() {
+        assert_eq!(PolarPlot::radius_in(&Rect::new(10, 10, 200, 100)), 50 - 15);
+    }
+
+    #[test]
+    fn point_mapping_test() {
+        let plot = plot();
+        assert_eq!(plot.to_display(Point { x: 0f64, y: 10f64 }),
+                   Some(DisplayPoint { x: 110 + 35, y: 60 }));
+        assert_eq!(plot.to_display(Point { x: PI / 2f64, y: 5f64 }),
+                   Some(DisplayPoint { x: 110, y: 60 + 18 }));
+        assert_eq!(plot.to_display(Point { x: PI, y: -3f64 }),
+                   Some(DisplayPoint { x: 110, y: 60 }));
+        assert_eq!(plot.to_display(Point { x: 0f64, y: 20f64 }), None);
+    }
+
+    #[test]
+    fn pixel_mapping_test() {
+        let plot = plot();
+        let p = plot.to_data(DisplayPoint { x: 110, y: 60 + 35 }).unwrap();
+        assert!((p.x - PI / 2f64).abs() < 1e-9 && (p.y - 10f64).abs() < 1e-9);
+        let p = plot.to_data(DisplayPoint { x: 110, y: 60 - 35 }).unwrap();
+        assert!((p.x - 3f64 * PI / 2f64).abs() < 1e-9);
+        assert_eq!(plot.to_data(DisplayPoint { x: 110 + 40, y: 60 }), None);
+    }
+
+    #[test]
+    fn grid_stays_in_plot_test() {
+        let rect = Rect::new(10, 10, 200, 100);
+        let points = plot().create_points();
+        assert!(points.iter().all(|p| rect.contains(*p)));
+    }
+}
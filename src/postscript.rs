@@ -0,0 +1,340 @@
+use std::io::Write;
+
+use Chart;
+use Color;
+use Rect;
+use Serie;
+use chart::{GraphError, InPoint, IterInPoint};
+use font::{SPACE_BETWEEN_CHARS, W_CHAR};
+use vector::{Drawing, Position, Text};
+
+const MAX_PATH: usize = 1000;     //points stroked at once, old interpreters limit paths
+const COURIER_ADVANCE: f64 = 0.6;     //glyph width of Courier in font sizes
+
+// size of Courier, a standard Type 1 font, whose glyphs are as wide as those
+// of the bitmap font
+const COURIER_SIZE: f64 = (W_CHAR + SPACE_BETWEEN_CHARS) as f64 / COURIER_ADVANCE;
+
+
+// operators of a PostScript or PDF page, positions in points from the bottom
+// left; lines have square caps so they cover the pixels the canvas ones do,
+// dotted ones butt caps
+trait Page {
+    fn color(&mut self, color: Color);
+    fn rect(&mut self, rect: &Rect);
+    fn path(&mut self, points: &[Position], fill: bool);
+    fn dotted(&mut self, dotted: bool);
+    fn text(&mut self, text: &Text);
+    fn clip(&mut self, rect: &Rect);
+    fn unclip(&mut self);
+}
+
+struct Eps {
+    out: String,
+}
+
+struct Pdf {
+    out: String,
+}
+
+
+/// Writes the chart as an Encapsulated PostScript figure, one point for a
+/// pixel of the chart. Texts are set in Courier, a standard Type 1 font.
+pub fn write_eps<S, T, P, W>(chart: &Chart, series: S, mut writer: W) -> Result<(), GraphError>
+    where S: Iterator<Item = Serie<T, P>>,
+          T: IterInPoint<P>,
+          P: InPoint,
+          W: Write
+{
+    let drawing = Drawing::new(chart, series)?;
+    let mut eps = Eps { out: String::new() };
+    eps.out.push_str("%!PS-Adobe-3.0 EPSF-3.0\n");
+    eps.out.push_str(&format!("%%BoundingBox: 0 0 {} {}\n", drawing.width, drawing.height));
+    if let Some(title) = chart.title() {
+        eps.out.push_str(&format!("%%Title: {}\n", string(title)));
+    }
+    eps.out.push_str("%%Creator: simple-chart\n%%DocumentNeededResources: font Courier\n%%EndComments\n");
+    eps.out.push_str("gsave\n1 setlinewidth\n2 setlinecap\n1 setlinejoin\n");
+    eps.out.push_str(&format!("/Courier findfont {} scalefont setfont\n", number(COURIER_SIZE)));
+    paint(&drawing, &mut eps);
+    eps.out.push_str("grestore\nshowpage\n%%EOF\n");
+
+    writer.write_all(eps.out.as_bytes())?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes the chart as a single page PDF of vector paths, one point for a
+/// pixel of the chart. Texts are set in Courier, a standard Type 1 font
+/// every reader has, so nothing is embedded.
+pub fn write_pdf<S, T, P, W>(chart: &Chart, series: S, mut writer: W) -> Result<(), GraphError>
+    where S: Iterator<Item = Serie<T, P>>,
+          T: IterInPoint<P>,
+          P: InPoint,
+          W: Write
+{
+    let drawing = Drawing::new(chart, series)?;
+    let mut pdf = Pdf { out: String::from("1 w\n2 J\n1 j\n") };
+    paint(&drawing, &mut pdf);
+
+    let objects = [String::from("<< /Type /Catalog /Pages 2 0 R >>"),
+                       String::from("<< /Type /Pages /Kids [3 0 R] /Count 1 >>"),
+                       format!("<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+                                /Resources << /Font << /F1 5 0 R >> >> /Contents 4 0 R >>",
+                               drawing.width,
+                               drawing.height),
+                       format!("<< /Length {} >>\nstream\n{}endstream", pdf.out.len(), pdf.out),
+                       String::from("<< /Type /Font /Subtype /Type1 /BaseFont /Courier \
+                                     /Encoding /WinAnsiEncoding >>")];
+
+    // the binary comment marks the file as binary for transfer programs
+    let mut file = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+    let mut offsets = vec![];
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(file.len());
+        file.extend_from_slice(format!("{} 0 obj\n{}\nendobj\n", i + 1, object).as_bytes());
+    }
+    let xref = file.len();
+    file.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+    for offset in offsets {
+        file.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    file.extend_from_slice(format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+                                   objects.len() + 1,
+                                   xref)
+        .as_bytes());
+
+    writer.write_all(&file)?;
+    writer.flush()?;
+    Ok(())
+}
+
+// the same shapes in the same order as `Chart::draw` draws them
+fn paint<G: Page>(drawing: &Drawing, page: &mut G) {
+    page.color(drawing.background);
+    page.rect(&Rect::new(0, 0, drawing.width, drawing.height));
+
+    page.color(drawing.axis_color);
+    page.dotted(true);
+    for &(from, to) in &drawing.grid {
+        page.path(&[from, to], false);
+    }
+    page.dotted(false);
+    for &(from, to) in &drawing.lines {
+        page.path(&[from, to], false);
+    }
+    for arrow in &drawing.arrows {
+        page.path(arrow, true);
+    }
+    for text in &drawing.texts {
+        page.text(text);
+    }
+
+    page.clip(&drawing.clip());
+    for line in &drawing.series {
+        page.color(line.color);
+        for part in &line.parts {
+            page.path(part, false);
+        }
+    }
+    page.unclip();
+
+    for entry in &drawing.legend {
+        page.color(drawing.series[entry.serie].color);
+        page.path(&[entry.swatch.0, entry.swatch.1], false);
+        page.color(drawing.axis_color);
+        page.text(&entry.text);
+    }
+}
+
+impl Page for Eps {
+    fn color(&mut self, color: Color) {
+        self.out.push_str(&format!("{} setrgbcolor\n", rgb(color)));
+    }
+
+    fn rect(&mut self, rect: &Rect) {
+        self.out.push_str(&format!("{} {} {} {} rectfill\n", rect.x, rect.y, rect.width, rect.height));
+    }
+
+    fn path(&mut self, points: &[Position], fill: bool) {
+        if points.len() < 2 {
+            return;
+        }
+        for (i, &(x, y)) in points.iter().enumerate() {
+            let operator = if i == 0 { "moveto" } else { "lineto" };
+            self.out.push_str(&format!("{} {} {}\n", number(x), number(y), operator));
+            // long lines go on from where the stroked part ends
+            if !fill && i > 0 && i % MAX_PATH == 0 && i + 1 < points.len() {
+                self.out.push_str("currentpoint stroke moveto\n");
+            }
+        }
+        self.out.push_str(if fill { "closepath fill\n" } else { "stroke\n" });
+    }
+
+    fn dotted(&mut self, dotted: bool) {
+        self.out.push_str(if dotted { "gsave 0 setlinecap [1 1] 0 setdash\n" } else { "grestore\n" });
+    }
+
+    fn text(&mut self, text: &Text) {
+        let (x, y) = text.position;
+        if text.vertical {
+            self.out.push_str(&format!("gsave {} {} translate 90 rotate 0 0 moveto {} show grestore\n",
+                                       number(x),
+                                       number(y),
+                                       string(&text.text)));
+        } else {
+            self.out.push_str(&format!("{} {} moveto {} show\n", number(x), number(y), string(&text.text)));
+        }
+    }
+
+    fn clip(&mut self, rect: &Rect) {
+        self.out.push_str(&format!("gsave {} {} {} {} rectclip\n", rect.x, rect.y, rect.width, rect.height));
+    }
+
+    fn unclip(&mut self) {
+        self.out.push_str("grestore\n");
+    }
+}
+
+impl Page for Pdf {
+    fn color(&mut self, color: Color) {
+        let rgb = rgb(color);
+        self.out.push_str(&format!("{} RG {} rg\n", rgb, rgb));
+    }
+
+    fn rect(&mut self, rect: &Rect) {
+        self.out.push_str(&format!("{} {} {} {} re f\n", rect.x, rect.y, rect.width, rect.height));
+    }
+
+    fn path(&mut self, points: &[Position], fill: bool) {
+        if points.len() < 2 {
+            return;
+        }
+        for (i, &(x, y)) in points.iter().enumerate() {
+            let operator = if i == 0 { "m" } else { "l" };
+            self.out.push_str(&format!("{} {} {}\n", number(x), number(y), operator));
+        }
+        self.out.push_str(if fill { "h f\n" } else { "S\n" });
+    }
+
+    fn dotted(&mut self, dotted: bool) {
+        self.out.push_str(if dotted { "q 0 J [1 1] 0 d\n" } else { "Q\n" });
+    }
+
+    fn text(&mut self, text: &Text) {
+        let (x, y) = text.position;
+        // a text matrix turning glyphs a quarter to the left for vertical ones
+        let matrix = if text.vertical { "0 1 -1 0" } else { "1 0 0 1" };
+        self.out.push_str(&format!("BT /F1 {} Tf {} {} {} Tm {} Tj ET\n",
+                                   number(COURIER_SIZE),
+                                   matrix,
+                                   number(x),
+                                   number(y),
+                                   string(&text.text)));
+    }
+
+    fn clip(&mut self, rect: &Rect) {
+        self.out.push_str(&format!("q {} {} {} {} re W n\n", rect.x, rect.y, rect.width, rect.height));
+    }
+
+    fn unclip(&mut self) {
+        self.out.push_str("Q\n");
+    }
+}
+
+fn rgb(color: Color) -> String {
+    format!("{} {} {}",
+            number(color.r as f64 / 255f64),
+            number(color.g as f64 / 255f64),
+            number(color.b as f64 / 255f64))
+}
+
+// shortest of two decimal places
+fn number(value: f64) -> String {
+    let text = format!("{:.2}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    if text == "-0" {
+        String::from("0")
+    } else {
+        String::from(text)
+    }
+}
+
+// string literal of both languages, characters out of ASCII as '?'
+fn string(text: &str) -> String {
+    let mut literal = String::from("(");
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                literal.push('\\');
+                literal.push(c);
+            }
+            ' '..='~' => literal.push(c),
+            _ => literal.push('?'),
+        }
+    }
+    literal.push(')');
+    literal
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Point;
+    use chart::Points;
+
+    fn series() -> Vec<Points> {
+        let points = vec![Point { x: 0f64, y: 0f64 }, Point { x: 1f64, y: 2f64 }, Point { x: 2f64, y: 1f64 }];
+        vec![Serie::new(points.into_iter(), "#ff0000").unwrap().add_name("a (b)")]
+    }
+
+    #[test]
+    fn number_and_string_test() {
+        assert_eq!(number(12.5), "12.5");
+        assert_eq!(number(3.0), "3");
+        assert_eq!(number(-0.001), "0");
+        assert_eq!(string("f(x) \\ é"), "(f\\(x\\) \\\\ ?)");
+    }
+
+    #[test]
+    fn eps_test() {
+        let chart = Chart::new(200, 150, "#ffffff", "#000000").unwrap().add_title("Title");
+        let mut out = vec![];
+        write_eps(&chart, series().into_iter(), &mut out).unwrap();
+        let eps = String::from_utf8(out).unwrap();
+
+        assert!(eps.starts_with("%!PS-Adobe-3.0 EPSF-3.0\n%%BoundingBox: 0 0 200 150\n"));
+        assert!(eps.ends_with("showpage\n%%EOF\n"));
+        assert!(eps.contains("(Title) show"));
+        assert!(eps.contains("(a \\(b\\)) show"));
+        assert!(eps.contains("1 0 0 setrgbcolor"));
+        assert_eq!(eps.matches("gsave").count(), eps.matches("grestore").count());
+    }
+
+    #[test]
+    fn pdf_test() {
+        let chart = Chart::new(200, 150, "#ffffff", "#000000").unwrap();
+        let mut out = vec![];
+        write_pdf(&chart, series().into_iter(), &mut out).unwrap();
+        // one character for a byte, keeping offsets
+        let pdf = out.iter().map(|&b| if b < 128 { b as char } else { '?' }).collect::<String>();
+
+        assert!(pdf.starts_with("%PDF-1.4\n"));
+        assert!(pdf.contains("/MediaBox [0 0 200 150]"));
+        assert!(pdf.contains("/BaseFont /Courier"));
+        // the cross reference table points at every object
+        let xref = pdf[pdf.rfind("startxref\n").unwrap() + 10..].lines().next().unwrap();
+        assert!(pdf[xref.parse::<usize>().unwrap()..].starts_with("xref\n0 6\n"));
+        for i in 1..6 {
+            let entry = &pdf[pdf.find("65535 f \n").unwrap() + 9 + (i - 1) * 20..][..10];
+            let offset = entry.parse::<usize>().unwrap();
+            assert!(pdf[offset..].starts_with(&format!("{} 0 obj", i)));
+        }
+
+        let length = &pdf[pdf.find("/Length ").unwrap() + 8..];
+        let length = length[..length.find(' ').unwrap()].parse::<usize>().unwrap();
+        let stream = &pdf[pdf.find("stream\n").unwrap() + 7..pdf.find("endstream").unwrap()];
+        assert_eq!(stream.len(), length);
+    }
+}
@@ -0,0 +1,286 @@
+use std::f64;
+use std::vec;
+
+use Axis;
+use Chart;
+use Rect;
+use Serie;
+
+const PIXELS_BETWEEN_SAMPLES: usize = 8;     //initial distance between samples in pixels
+const MIN_SAMPLES: usize = 8;
+const MAX_DEVIATION: f64 = 0.5;     //allowed distance from the drawn line in pixels
+const MAX_ANGLE: f64 = 0.2;     //allowed turn between two segments in radians
+const MIN_STEP: f64 = 0.25;     //interval width in pixels not divided any more
+const MAX_DEPTH: u32 = 24;
+const JUMP_DEPTH: u32 = 32;     //bisections done to tell a jump from a steep slope
+const MAX_JUMP: f64 = 1.0;     //pixels between neighbour points of a continuous line
+
+type Sample = (f64, f64);
+
+/// Samples a function densely where its line bends and sparsely where it
+/// is flat, with a tolerance in pixels of the picture it will be drawn on.
+///
+/// Discontinuities (like poles of `tan`) and undefined values split the
+/// line into several segments, so no vertical spike joins them.
+#[derive(Debug, Clone, Copy)]
+pub struct AdaptiveSampler {
+    start: f64,
+    stop: f64,
+    width: usize,
+    height: usize,
+    range_y: Option<(f64, f64)>,
+}
+
+impl AdaptiveSampler {
+    /// Sampler for x in `[start, stop]` drawn `width` x `height` pixels large.
+    pub fn new(start: f64, stop: f64, width: usize, height: usize) -> AdaptiveSampler {
+        AdaptiveSampler {
+            start,
+            stop,
+            width,
+            height,
+            range_y: None,
+        }
+    }
+
+    /// Sampler fitted to the plot size and the manual y axis of `chart`.
+    pub fn for_chart(chart: &Chart, start: f64, stop: f64) -> AdaptiveSampler {
+        // values are unknown before sampling, labels of a unit y axis stand
+        // in for those the plot is laid out with
+        let area = Rect::new(0, 0, chart.width(), chart.height());
+        let (layout, _, _) = chart.layout_with(area, &[], |width, height| {
+            let axis_x = match chart.axis_x() {
+                Some(axis) => axis.fit(width),
+                None => Axis::set_axis_auto(stop, start, width),
+            };
+            let axis_y = match chart.axis_y() {
+                Some(axis) => axis.fit(height),
+                None => Axis::set_axis_auto(1f64, 0f64, height).rotate(),
+            };
+            (axis_x, axis_y)
+        });
+        let sampler = AdaptiveSampler::new(start, stop, layout.plot.width, layout.plot.height);
+        match chart.axis_y() {
+            Some(axis) => sampler.range_y(axis.min_value, axis.max_value),
+            None => sampler,
+        }
+    }
+
+    /// Range of values shown on the y axis. Without it the range is
+    /// estimated from the function itself.
+    pub fn range_y(self, min: f64, max: f64) -> AdaptiveSampler {
+        AdaptiveSampler { range_y: Some((min, max)), ..self }
+    }
+
+    /// Continuous pieces of the function line, ordered by x.
+    pub fn sample<F>(&self, f: F) -> Vec<Vec<(f64, f64)>>
+        where F: Fn(f64) -> f64
+    {
+        let intervals = {
+            let count = self.width / PIXELS_BETWEEN_SAMPLES;
+            if count < MIN_SAMPLES { MIN_SAMPLES } else { count }
+        };
+        let samples = (0..(intervals + 1))
+            .map(|i| {
+                let x = self.start + (self.stop - self.start) * (i as f64) / (intervals as f64);
+                (x, f(x))
+            })
+            .collect::<Vec<Sample>>();
+
+        let (min_y, max_y) = self.range_y.unwrap_or_else(|| estimate_range(&samples));
+        let scale = Scale {
+            x: (self.width as f64) / (self.stop - self.start).abs(),
+            y: (self.height as f64) / (max_y - min_y),
+        };
+
+        let mut segments = Segments {
+            done: vec![],
+            current: vec![],
+        };
+        segments.start(samples[0]);
+        for pair in samples.windows(2) {
+            refine(&f, &scale, pair[0], pair[1], 0, &mut segments);
+        }
+        segments.finish()
+    }
+
+    /// Continuous pieces of the function line, each as a serie of `color`.
+    /// Pieces with a single point are left out.
+    pub fn series<F, S>(&self, f: F, color: S) -> Vec<Serie<vec::IntoIter<Sample>, Sample>>
+        where F: Fn(f64) -> f64,
+              S: Into<String>
+    {
+        let color = color.into();
+        self.sample(f)
+            .into_iter()
+            .filter_map(|segment| Serie::new(segment.into_iter(), &*color).ok())
+            .collect()
+    }
+}
+
+
+struct Scale {
+    x: f64,
+    y: f64,
+}
+
+struct Segments {
+    done: Vec<Vec<Sample>>,
+    current: Vec<Sample>,
+}
+
+impl Segments {
+    fn start(&mut self, p: Sample) {
+        if !self.current.is_empty() {
+            let segment = ::std::mem::take(&mut self.current);
+            self.done.push(segment);
+        }
+        if p.1.is_finite() {
+            self.current.push(p);
+        }
+    }
+
+    fn push(&mut self, p: Sample) {
+        self.current.push(p);
+    }
+
+    fn finish(mut self) -> Vec<Vec<Sample>> {
+        self.start((0f64, f64::NAN));
+        self.done
+    }
+}
+
+
+fn refine<F>(f: &F, scale: &Scale, a: Sample, b: Sample, depth: u32, segments: &mut Segments)
+    where F: Fn(f64) -> f64
+{
+    let x = (a.0 + b.0) / 2f64;
+    let m = (x, f(x));
+
+    let divisible = (b.0 - a.0).abs() * scale.x > MIN_STEP && depth < MAX_DEPTH;
+    let defined = a.1.is_finite() && b.1.is_finite() && m.1.is_finite();
+
+    if divisible && (!defined || bends(scale, a, m, b)) {
+        refine(f, scale, a, m, depth + 1, segments);
+        refine(f, scale, m, b, depth + 1, segments);
+    } else if !defined || is_jump(f, scale, a, b) {
+        segments.start(b);
+    } else {
+        segments.push(b);
+    }
+}
+
+// whether `m` lies too far from the line a-b, or the line turns too much in `m`
+fn bends(scale: &Scale, a: Sample, m: Sample, b: Sample) -> bool {
+    let deviation = (m.1 - (a.1 + b.1) / 2f64).abs() * scale.y;
+    if deviation > MAX_DEVIATION {
+        return true;
+    }
+    let angle_am = ((m.1 - a.1) * scale.y).atan2((m.0 - a.0) * scale.x);
+    let angle_mb = ((b.1 - m.1) * scale.y).atan2((b.0 - m.0) * scale.x);
+    let length = ((b.0 - a.0) * scale.x).hypot((b.1 - a.1) * scale.y);
+    length > 1f64 && (angle_am - angle_mb).abs() > MAX_ANGLE
+}
+
+// Neighbour values of a steep continuous function get closer when the
+// interval shrinks, those on both sides of a jump do not.
+fn is_jump<F>(f: &F, scale: &Scale, a: Sample, b: Sample) -> bool
+    where F: Fn(f64) -> f64
+{
+    let (mut a, mut b) = (a, b);
+    if (b.1 - a.1).abs() * scale.y <= MAX_JUMP {
+        return false;
+    }
+    for _ in 0..JUMP_DEPTH {
+        let x = (a.0 + b.0) / 2f64;
+        let m = (x, f(x));
+        if !m.1.is_finite() {
+            return true;
+        }
+        if (m.1 - a.1).abs() > (b.1 - m.1).abs() {
+            b = m;
+        } else {
+            a = m;
+        }
+        if (b.1 - a.1).abs() * scale.y <= MAX_JUMP {
+            return false;
+        }
+    }
+    true
+}
+
+fn estimate_range(samples: &[Sample]) -> (f64, f64) {
+    let (mut min, mut max) = (f64::INFINITY, f64::NEG_INFINITY);
+    for &(_, y) in samples.iter().filter(|p| p.1.is_finite()) {
+        if y < min {
+            min = y;
+        }
+        if y > max {
+            max = y;
+        }
+    }
+    if min > max {
+        (-1f64, 1f64)
+    } else if min == max {
+        (min - 1f64, max + 1f64)
+    } else {
+        (min, max)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flat_line_takes_few_points_test() {
+        let segments = AdaptiveSampler::new(0.0, 10.0, 400, 300).sample(|x| 2.0 * x + 1.0);
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].len(), 400 / 8 + 1);
+        assert_eq!(segments[0][0], (0.0, 1.0));
+        assert_eq!(*segments[0].last().unwrap(), (10.0, 21.0));
+    }
+
+    #[test]
+    fn steep_parts_get_more_points_test() {
+        let segments = AdaptiveSampler::new(-1.0, 1.0, 400, 400)
+            .sample(|x| (1f64 - x * x).sqrt());
+        assert_eq!(segments.len(), 1);
+        let points = &segments[0];
+        let near_edge = points.iter().filter(|p| p.0 > 0.9).count();
+        let near_middle = points.iter().filter(|p| p.0.abs() < 0.1).count();  // twice as wide
+        assert!(near_edge > 2 * near_middle);
+        assert_eq!(*points.last().unwrap(), (1.0, 0.0));
+    }
+
+    #[test]
+    fn chart_sampler_fits_plot_test() {
+        let chart = Chart::new(400, 300, "#ffffff", "#000000").unwrap().add_title("Title");
+        let sampler = AdaptiveSampler::for_chart(&chart, 0.0, 10.0);
+        assert!(sampler.width < 400 && sampler.width > 300);
+        assert!(sampler.height < 300 && sampler.height > 200);
+    }
+
+    #[test]
+    fn poles_split_line_test() {
+        let segments = AdaptiveSampler::new(-3.0, 3.0, 400, 300)
+            .range_y(-5.0, 5.0)
+            .sample(|x| x.tan());
+        assert_eq!(segments.len(), 3);
+        for segment in &segments {
+            assert!(segment.windows(2).all(|w| w[1].0 > w[0].0));
+        }
+        assert!(segments[0].last().unwrap().1 > 5.0);
+        assert!(segments[1].first().unwrap().1 < -5.0);
+    }
+
+    #[test]
+    fn undefined_values_split_line_test() {
+        let sampler = AdaptiveSampler::new(-2.0, 2.0, 200, 200);
+        let segments = sampler.sample(|x| (x * x - 1f64).sqrt());
+        assert_eq!(segments.len(), 2);
+        assert!(segments.iter().flat_map(|s| s.iter()).all(|p| p.0.abs() >= 1.0));
+        assert_eq!(sampler.series(|x| (x * x - 1f64).sqrt(), "#ff0000").len(), 2);
+    }
+}
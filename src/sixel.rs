@@ -0,0 +1,111 @@
+use std::io::{self, Write};
+
+use Canvas;
+
+const BAND_HEIGHT: usize = 6;     //pixel rows in one sixel
+const SIXEL_OFFSET: u8 = 63;     //character of the empty sixel, '?'
+const MIN_REPEAT: usize = 4;     //runs shorter than this are cheaper written out
+
+
+/// Writes `canvas` as a DEC Sixel image. Colors of the canvas palette
+/// become the sixel color registers of the same indices.
+pub fn write_sixel<W: Write>(canvas: &Canvas, writer: &mut W) -> io::Result<()> {
+    let (width, height) = (canvas.width(), canvas.height());
+
+    // 7 bit DCS, pixel aspect 1:1, then raster attributes
+    write!(writer, "\x1bP0;1;0q\"1;1;{};{}", width, height)?;
+    for (i, color) in canvas.palette().iter().enumerate() {
+        write!(writer,
+               "#{};2;{};{};{}",
+               i,
+               percent(color.r),
+               percent(color.g),
+               percent(color.b))?;
+    }
+
+    let pixels = canvas.pixels();
+    let mut used = vec![false; canvas.palette().len()];
+    let mut line = vec![0u8; width];
+    for band in 0..height.div_ceil(BAND_HEIGHT) {
+        // rows of the canvas start from the bottom, sixels from the top
+        let rows = (0..BAND_HEIGHT)
+            .map(|k| band * BAND_HEIGHT + k)
+            .take_while(|&row| row < height)
+            .map(|row| height - 1 - row)
+            .collect::<Vec<usize>>();
+
+        used.fill(false);
+        for &y in &rows {
+            for &p in &pixels[y * width..(y + 1) * width] {
+                used[p as usize] = true;
+            }
+        }
+
+        let mut first = true;
+        for color in (0..used.len()).filter(|&c| used[c]) {
+            for (x, sixel) in line.iter_mut().enumerate() {
+                *sixel = 0;
+                for (bit, &y) in rows.iter().enumerate() {
+                    if pixels[y * width + x] as usize == color {
+                        *sixel |= 1 << bit;
+                    }
+                }
+            }
+            if !first {
+                writer.write_all(b"$")?;
+            }
+            first = false;
+            write!(writer, "#{}", color)?;
+            write_runs(&line, writer)?;
+        }
+        writer.write_all(b"-")?;
+    }
+
+    writer.write_all(b"\x1b\\")
+}
+
+fn percent(channel: u8) -> u32 {
+    ((channel as f64) * 100f64 / 255f64).round() as u32
+}
+
+// sixels of one color, repeated ones as `!count`, trailing empty ones left out
+fn write_runs<W: Write>(line: &[u8], writer: &mut W) -> io::Result<()> {
+    let end = line.iter().rposition(|&s| s != 0).map_or(0, |i| i + 1);
+    let mut x = 0;
+    while x < end {
+        let sixel = line[x];
+        let run = line[x..end].iter().take_while(|&&s| s == sixel).count();
+        let c = SIXEL_OFFSET + sixel;
+        if run >= MIN_REPEAT {
+            write!(writer, "!{}{}", run, c as char)?;
+        } else {
+            for _ in 0..run {
+                writer.write_all(&[c])?;
+            }
+        }
+        x += run;
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use DisplayPoint;
+
+    #[test]
+    fn sixel_test() {
+        let mut canvas = Canvas::new(5, 7, "#ffffff");
+        let red = canvas.add_color("#ff0000");
+        // top left pixel and the bottom row
+        canvas.draw_pixels(vec![DisplayPoint { x: 0, y: 6 }], red);
+        canvas.draw_pixels((0..5).map(|x| DisplayPoint { x, y: 0 }), red);
+
+        let mut out = vec![];
+        write_sixel(&canvas, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "\x1bP0;1;0q\"1;1;5;7#0;2;100;100;100#1;2;100;0;0\
+                    #0}!4~$#1@-#1!5@-\x1b\\");
+    }
+}
@@ -0,0 +1,372 @@
+use std::cmp::Ordering;
+use std::f64;
+use std::vec;
+
+use serde::{Deserialize, Deserializer};
+use serde_json;
+use toml;
+
+use Axis;
+use Chart;
+use Color;
+use Coordinates;
+use Expression;
+use Point;
+use Serie;
+use chart::{GraphError, GraphResult, InPoint, IterInPoint};
+
+
+/// Chart described by a JSON or TOML document:
+///
+/// ```json
+/// {
+///     "width": 740,
+///     "height": 480,
+///     "title": "Sine",
+///     "axis_y": { "min": -1, "max": 1, "interval_count": 4, "decimal_places": 1 },
+///     "series": [
+///         { "color": "#ff0000", "formula": { "expression": "sin(x)", "start": 0, "stop": 10, "step": 0.1 } },
+///         { "color": "#0000ff", "name": "data", "points": [[0, 0], [5, 0.5], [10, -0.5]] }
+///     ]
+/// }
+/// ```
+///
+/// Colors default to a black axis on white, axes are calculated from the
+/// series when they are not given.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChartSpec {
+    pub width: usize,
+    pub height: usize,
+    #[serde(default = "default_background_color")]
+    pub background_color: String,
+    #[serde(default = "default_axis_color")]
+    pub axis_color: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub axis_x_title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub axis_y_title: Option<String>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub polar: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub axis_x: Option<AxisSpec>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub axis_y: Option<AxisSpec>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub series: Vec<SerieSpec>,
+}
+
+/// Arguments of `Axis::new`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AxisSpec {
+    pub min: f64,
+    pub max: f64,
+    pub interval_count: u8,
+    #[serde(default)]
+    pub decimal_places: u8,
+}
+
+/// Serie given either by its points or by a formula of `x`. Gaps in the
+/// points are NaN, which JSON writes as `null`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SerieSpec {
+    pub color: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "points_with_gaps")]
+    pub points: Option<Vec<(f64, f64)>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub formula: Option<FormulaSpec>,
+}
+
+/// Expression sampled like `Expression::formula` does.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FormulaSpec {
+    pub expression: String,
+    pub start: f64,
+    pub stop: f64,
+    pub step: f64,
+}
+
+
+impl ChartSpec {
+    pub fn from_json(json: &str) -> Result<ChartSpec, GraphError> {
+        serde_json::from_str(json).map_err(|err| GraphError::InvalidSpec(err.to_string()))
+    }
+
+    pub fn from_toml(toml: &str) -> Result<ChartSpec, GraphError> {
+        toml::from_str(toml).map_err(|err| GraphError::InvalidSpec(err.to_string()))
+    }
+
+    pub fn to_json(&self) -> Result<String, GraphError> {
+        serde_json::to_string_pretty(self).map_err(|err| GraphError::InvalidSpec(err.to_string()))
+    }
+
+    pub fn to_toml(&self) -> Result<String, GraphError> {
+        toml::to_string(self).map_err(|err| GraphError::InvalidSpec(err.to_string()))
+    }
+
+    /// Spec of the chart configuration, without series.
+    pub fn from_chart(chart: &Chart) -> ChartSpec {
+        ChartSpec {
+            width: chart.width(),
+            height: chart.height(),
+            background_color: chart.background_color().to_string(),
+            axis_color: chart.axis_color().to_string(),
+            title: chart.title().map(String::from),
+            axis_x_title: chart.axis_x_title().map(String::from),
+            axis_y_title: chart.axis_y_title().map(String::from),
+            polar: chart.coordinates() == Coordinates::Polar,
+            axis_x: chart.axis_x().map(AxisSpec::from_axis),
+            axis_y: chart.axis_y().map(AxisSpec::from_axis),
+            series: vec![],
+        }
+    }
+
+    /// Adds a serie with the points of `serie`.
+    pub fn add_serie<P, T>(mut self, serie: &Serie<T, P>) -> ChartSpec
+        where T: IterInPoint<P>,
+              P: InPoint
+    {
+        self.series.push(SerieSpec::from_serie(serie));
+        self
+    }
+
+    pub fn chart(&self) -> Result<Chart, GraphError> {
+        check_color(&self.background_color, "background_color")?;
+        check_color(&self.axis_color, "axis_color")?;
+
+        let mut chart = Chart::new(self.width, self.height, &self.background_color, &self.axis_color)?;
+        if let Some(ref axis) = self.axis_x {
+            chart = chart.add_axis_x(axis.axis("axis_x")?);
+        }
+        if let Some(ref axis) = self.axis_y {
+            chart = chart.add_axis_y(axis.axis("axis_y")?);
+        }
+        if let Some(ref title) = self.title {
+            chart = chart.add_title(&**title);
+        }
+        if let Some(ref title) = self.axis_x_title {
+            chart = chart.add_axis_x_title(&**title);
+        }
+        if let Some(ref title) = self.axis_y_title {
+            chart = chart.add_axis_y_title(&**title);
+        }
+        if self.polar {
+            chart = chart.polar();
+        }
+        Ok(chart)
+    }
+
+    pub fn series(&self) -> Result<Vec<Serie<vec::IntoIter<Point>, Point>>, GraphError> {
+        self.series
+            .iter()
+            .enumerate()
+            .map(|(i, serie)| serie.serie(&format!("series[{}]", i)))
+            .collect()
+    }
+
+    /// Bmp picture of the chart.
+    pub fn draw(&self) -> GraphResult {
+        let chart = self.chart()?;
+        let series = self.series()?;
+        // axes that are not set are calculated from the series
+        if series.is_empty() && (self.axis_x.is_none() || self.axis_y.is_none()) {
+            return Err(invalid("series", "there must be a serie unless both axes are set"));
+        }
+        Ok(chart.draw(series.into_iter()))
+    }
+}
+
+impl AxisSpec {
+    pub fn from_axis(axis: &Axis) -> AxisSpec {
+        AxisSpec {
+            min: axis.min_value,
+            max: axis.max_value,
+            interval_count: axis.interval_count,
+            decimal_places: axis.decimal_places,
+        }
+    }
+
+    fn axis(&self, path: &str) -> Result<Axis, GraphError> {
+        if self.min.partial_cmp(&self.max) != Some(Ordering::Less) {
+            return Err(invalid(path, "min must be less than max"));
+        }
+        if self.interval_count == 0 {
+            return Err(invalid(path, "interval_count must be positive"));
+        }
+        Ok(Axis::new(self.min, self.max, self.interval_count, self.decimal_places))
+    }
+}
+
+impl SerieSpec {
+    pub fn from_serie<P, T>(serie: &Serie<T, P>) -> SerieSpec
+        where T: IterInPoint<P>,
+              P: InPoint
+    {
+        let points = serie.iter.clone().map(|p| {
+            let p: Point = p.into();
+            (p.x, p.y)
+        });
+        SerieSpec {
+            color: serie.color().to_string(),
+            name: serie.name().map(String::from),
+            points: Some(points.collect()),
+            formula: None,
+        }
+    }
+
+    fn serie(&self, path: &str) -> Result<Serie<vec::IntoIter<Point>, Point>, GraphError> {
+        check_color(&self.color, &format!("{}.color", path))?;
+
+        let points = match (&self.points, &self.formula) {
+            (Some(points), None) => points.iter().map(Point::from).collect::<Vec<Point>>(),
+            (None, Some(formula)) => formula.points(&format!("{}.formula", path))?,
+            _ => return Err(invalid(path, "exactly one of points and formula must be given")),
+        };
+
+        let serie = Serie::new(points.into_iter(), &*self.color)
+            .map_err(|err| invalid(path, &err.to_string()))?;
+        Ok(match self.name {
+            Some(ref name) => serie.add_name(&**name),
+            None => serie,
+        })
+    }
+}
+
+impl FormulaSpec {
+    fn points(&self, path: &str) -> Result<Vec<Point>, GraphError> {
+        let expression = Expression::parse(&self.expression)
+            .map_err(|err| invalid(&format!("{}.expression", path), &err.to_string()))?;
+        if self.step.partial_cmp(&0f64) != Some(Ordering::Greater) {
+            return Err(invalid(path, "step must be positive"));
+        }
+        Ok(expression.formula(self.start, self.stop, self.step).map(Point::from).collect())
+    }
+}
+
+
+fn default_background_color() -> String {
+    "#ffffff".to_string()
+}
+
+fn default_axis_color() -> String {
+    "#000000".to_string()
+}
+
+// `null` coordinates, as JSON writes NaN, are gaps
+fn points_with_gaps<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<(f64, f64)>>, D::Error> {
+    let points: Option<Vec<(Option<f64>, Option<f64>)>> = Deserialize::deserialize(deserializer)?;
+    Ok(points.map(|points| {
+        points.into_iter()
+            .map(|(x, y)| (x.unwrap_or(f64::NAN), y.unwrap_or(f64::NAN)))
+            .collect()
+    }))
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+fn invalid(path: &str, message: &str) -> GraphError {
+    GraphError::InvalidSpec(format!("{}: {}", path, message))
+}
+
+fn check_color(color: &str, path: &str) -> Result<(), GraphError> {
+    color.parse::<Color>()
+        .map(|_| ())
+        .map_err(|_| invalid(path, &format!("'{}' is not a color like #rrggbb", color)))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JSON: &str = r##"{
+        "width": 300,
+        "height": 200,
+        "title": "Sine",
+        "axis_y": { "min": -1, "max": 1, "interval_count": 4, "decimal_places": 1 },
+        "series": [
+            { "color": "#ff0000", "formula": { "expression": "sin(x)", "start": 0, "stop": 1, "step": 0.5 } },
+            { "color": "#0000ff", "name": "data", "points": [[0, 0], [5, 0.5]] }
+        ]
+    }"##;
+
+    #[test]
+    fn json_spec_test() {
+        let spec = ChartSpec::from_json(JSON).unwrap();
+        let chart = spec.chart().unwrap();
+        assert_eq!(chart.width(), 300);
+        assert_eq!(chart.title(), Some("Sine"));
+        assert_eq!(chart.axis_y().unwrap().interval_count, 4);
+        assert!(chart.axis_x().is_none());
+
+        let series = spec.series().unwrap();
+        assert_eq!(series.len(), 2);
+        assert_eq!(series[0].iter.clone().count(), 3);
+        assert_eq!(series[1].name(), Some("data"));
+        assert!(spec.draw().is_ok());
+    }
+
+    #[test]
+    fn round_trip_test() {
+        let chart = Chart::new(300, 200, "#FFFFF0", "#000000")
+            .unwrap()
+            .add_axis_x(Axis::new(0f64, 10f64, 5, 0))
+            .add_axis_x_title("time")
+            .polar();
+        let serie = Serie::new(vec![(0f64, 1f64), (1f64, 2f64)].into_iter(), "#00ff00").unwrap();
+        let spec = ChartSpec::from_chart(&chart).add_serie(&serie);
+        assert_eq!(spec.background_color, "#fffff0");
+        assert!(spec.polar);
+
+        let from_json = ChartSpec::from_json(&spec.to_json().unwrap()).unwrap();
+        assert_eq!(from_json, spec);
+        let from_toml = ChartSpec::from_toml(&spec.to_toml().unwrap()).unwrap();
+        assert_eq!(from_toml, spec);
+
+        // NaN never equals itself, so gaps are compared as they are written
+        let gaps = vec![(0f64, 1f64), (1f64, f64::NAN), (2f64, 2f64), (3f64, 3f64)];
+        let spec = ChartSpec::from_chart(&chart).add_serie(&Serie::new(gaps.into_iter(), "#00ff00").unwrap());
+        let json = spec.to_json().unwrap();
+        assert_eq!(ChartSpec::from_json(&json).unwrap().to_json().unwrap(), json);
+        let toml = spec.to_toml().unwrap();
+        assert_eq!(ChartSpec::from_toml(&toml).unwrap().to_toml().unwrap(), toml);
+        assert!(ChartSpec::from_json(&json).unwrap().series[0].points.as_ref().unwrap()[1].1.is_nan());
+    }
+
+    #[test]
+    fn invalid_spec_test() {
+        let error = |json: &str| ChartSpec::from_json(json).and_then(|s| s.draw()).err().unwrap().to_string();
+
+        assert!(error(r#"{ "width": 300 }"#).contains("missing field `height`"));
+        assert!(error(r##"{ "width": 300, "height": 200, "colour": "#ff0000" }"##)
+            .contains("unknown field `colour`"));
+        assert_eq!(error(r#"{ "width": 300, "height": 200, "axis_color": "red" }"#),
+                   "Invalid chart specification: axis_color: 'red' is not a color like #rrggbb");
+        assert_eq!(error(r#"{ "width": 300, "height": 200,
+                             "axis_x": { "min": 1, "max": 1, "interval_count": 2 } }"#),
+                   "Invalid chart specification: axis_x: min must be less than max");
+        assert_eq!(error(r##"{ "width": 300, "height": 200,
+                              "series": [{ "color": "#ff0000" }] }"##),
+                   "Invalid chart specification: series[0]: exactly one of points and formula \
+                    must be given");
+        assert_eq!(error(r#"{ "width": 100, "height": 100, "series": [] }"#),
+                   "Invalid chart specification: series: there must be a serie unless both axes are set");
+        let axes = r#"{ "width": 100, "height": 100,
+                        "axis_x": { "min": 0, "max": 1, "interval_count": 2 },
+                        "axis_y": { "min": 0, "max": 1, "interval_count": 2 } }"#;
+        assert!(ChartSpec::from_json(axes).unwrap().draw().is_ok());
+        assert_eq!(error(r##"{ "width": 300, "height": 200, "series": [{ "color": "#ff0000",
+                              "formula": { "expression": "sin(", "start": 0, "stop": 1, "step": 1 } }] }"##),
+                   "Invalid chart specification: series[0].formula.expression: \
+                    Invalid expression at position 5: unexpected end of expression");
+    }
+}
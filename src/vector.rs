@@ -0,0 +1,260 @@
+use Axis;
+use Chart;
+use Color;
+use Coordinates;
+use Point;
+use Rect;
+use Serie;
+use chart::{GraphError, InPoint, IterInPoint, Points, to_cartesian, finite_parts};
+use font::{self, H_CHAR};
+use layout::{H_ARROW_HALF, W_ARROW, W_GAP, W_TICK};
+
+pub type Position = (f64, f64);
+
+
+/// Text starting at `position`, on its baseline.
+#[derive(Debug, Clone)]
+pub struct Text {
+    pub position: Position,
+    pub text: String,
+    pub width: usize,
+    pub vertical: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Line {
+    pub name: Option<String>,
+    pub color: Color,
+    pub values: Vec<(f64, f64)>,
+    pub parts: Vec<Vec<Position>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub serie: usize,
+    pub swatch: (Position, Position),
+    pub text: Text,
+    pub area: Rect,
+}
+
+/// Chart laid out and calculated like `Chart::draw` does, as shapes for the
+/// vector formats rather than pixels. Positions are in pixels of the chart,
+/// y going up as on the canvas, pixel `(x, y)` having its center at
+/// `(x + 0.5, y + 0.5)`.
+#[derive(Debug, Clone)]
+pub struct Drawing {
+    pub width: usize,
+    pub height: usize,
+    pub background: Color,
+    pub axis_color: Color,
+    pub plot: Rect,
+    pub axis_x: Axis,
+    pub axis_y: Axis,
+    pub lines: Vec<(Position, Position)>,
+    pub grid: Vec<(Position, Position)>,
+    pub arrows: Vec<[Position; 3]>,
+    pub texts: Vec<Text>,
+    pub series: Vec<Line>,
+    pub legend: Vec<Entry>,
+}
+
+impl Drawing {
+    pub fn new<S, T, P>(chart: &Chart, series: S) -> Result<Drawing, GraphError>
+        where S: Iterator<Item = Serie<T, P>>,
+              T: IterInPoint<P>,
+              P: InPoint
+    {
+        // polar charts are drawn with their points converted to x and y
+        let series = if chart.coordinates() == Coordinates::Polar {
+            series.filter_map(|s| to_cartesian(&s)).collect::<Vec<Points>>()
+        } else {
+            series.map(|s| s.collect_points()).collect::<Vec<Points>>()
+        };
+        if series.is_empty() {
+            return Err(GraphError::NotEnoughPoints);
+        }
+        let names = series.iter().filter_map(|s| s.name().map(String::from)).collect::<Vec<String>>();
+
+        let area = Rect::new(0, 0, chart.width(), chart.height());
+        let (layout, axis_x, axis_y) =
+            chart.layout_with(area, &names, |width, height| chart.fit_cartesian_axes(&series, width, height));
+        let plot = layout.plot;
+
+        let mut drawing = Drawing {
+            width: chart.width(),
+            height: chart.height(),
+            background: chart.background_color(),
+            axis_color: chart.axis_color(),
+            plot,
+            axis_x,
+            axis_y,
+            lines: vec![],
+            grid: vec![],
+            arrows: vec![],
+            texts: vec![],
+            series: vec![],
+            legend: vec![],
+        };
+        drawing.add_axes();
+
+        let texts = vec![(layout.title, chart.title(), false),
+                         (layout.axis_x_title, chart.axis_x_title(), false),
+                         (layout.axis_y_title, chart.axis_y_title(), true)];
+        for (rect, text, vertical) in texts {
+            if let (Some(rect), Some(text)) = (rect, text) {
+                drawing.texts.push(if vertical {
+                    // read from the bottom up, glyph tops to the left
+                    Text::new(text, (rect.x + H_CHAR) as f64, rect.y as f64, true)
+                } else {
+                    Text::new(text, rect.x as f64, rect.y as f64, false)
+                });
+            }
+        }
+
+        for serie in &series {
+            // the line breaks at gaps, a point between two of them is a dot,
+            // a line of no length stroked with square caps
+            let parts = finite_parts(serie.iter.clone())
+                .iter()
+                .map(|part| {
+                    let mut positions = part.iter()
+                        .map(|p| drawing.data_position(p.x, p.y))
+                        .collect::<Vec<Position>>();
+                    if positions.len() == 1 {
+                        positions.push(positions[0]);
+                    }
+                    positions
+                })
+                .collect();
+            drawing.series.push(Line {
+                name: serie.name().map(String::from),
+                color: Color::from(serie.color()),
+                values: serie.iter.clone().filter(Point::is_finite).map(|p| (p.x, p.y)).collect(),
+                parts,
+            });
+        }
+
+        let named = series.iter().enumerate().filter(|&(_, s)| s.name().is_some());
+        for (entry, (i, serie)) in named.enumerate() {
+            if let (Some(name), Some((swatch, text))) = (serie.name(), layout.legend_entry(entry)) {
+                let y = swatch.y as f64 + 0.5;
+                drawing.legend.push(Entry {
+                    serie: i,
+                    swatch: ((swatch.x as f64, y), (swatch.right() as f64, y)),
+                    text: Text::new(name, text.x as f64, text.y as f64, false),
+                    area: Rect::new(swatch.x, text.y, text.right() - swatch.x, H_CHAR + W_GAP),
+                });
+            }
+        }
+        Ok(drawing)
+    }
+
+    /// Position a point of the data lands on, not limited to the plot.
+    pub fn data_position(&self, x: f64, y: f64) -> Position {
+        let resolution_x = (self.axis_x.max_value - self.axis_x.min_value) / (self.plot.width as f64);
+        let resolution_y = (self.axis_y.max_value - self.axis_y.min_value) / (self.plot.height as f64);
+        (self.plot.x as f64 + 0.5 + (x - self.axis_x.min_value) / resolution_x,
+         self.plot.y as f64 + 0.5 + (y - self.axis_y.min_value) / resolution_y)
+    }
+
+    /// Area series are clipped to, the plot with the axes lines.
+    pub fn clip(&self) -> Rect {
+        Rect::new(self.plot.x, self.plot.y, self.plot.width + 1, self.plot.height + 1)
+    }
+
+    // the lines, arrows, ticks and labels `Axis::create_points` draws, and
+    // the minor net; its lines are to be dotted a pixel on and one off
+    fn add_axes(&mut self) {
+        let plot = self.plot;
+        let (left, bottom) = (plot.x as f64 + 0.5, plot.y as f64 + 0.5);
+        let end_x = (plot.right() + W_ARROW) as f64 + 0.5;
+        let end_y = (plot.top() + W_ARROW) as f64 + 0.5;
+        let arrow = H_ARROW_HALF as f64;
+        let tick = W_TICK as f64;
+
+        self.lines.push(((left, bottom), (end_x, bottom)));
+        self.lines.push(((left, bottom), (left, end_y)));
+        self.arrows.push([(end_x, bottom), (end_x - arrow, bottom + arrow), (end_x - arrow, bottom - arrow)]);
+        self.arrows.push([(left, end_y), (left - arrow, end_y - arrow), (left + arrow, end_y - arrow)]);
+
+        // the minor net fills odd pixels only, dotted lines start at one
+        let (first_x, first_y) = ((plot.x | 1) as f64, (plot.y | 1) as f64);
+        let (net_right, net_top) = ((plot.right() + 1) as f64, (plot.top() + 1) as f64);
+
+        let below = plot.y.saturating_sub(W_TICK + W_GAP + H_CHAR);
+        for (i, (x, label)) in ticks(&self.axis_x, plot.x).into_iter().zip(self.axis_x.labels()).enumerate() {
+            let center = x as f64 + 0.5;
+            self.lines.push(((center, bottom - tick), (center, bottom - 1f64)));
+            if i > 0 {
+                self.grid.push(((center, first_y), (center, net_top)));
+            }
+            let width = font::text_width(&label);
+            self.texts.push(Text::new(&label, x.saturating_sub(width / 2) as f64, below as f64, false));
+        }
+        let left_of = plot.x.saturating_sub(W_TICK + W_GAP);
+        for (i, (y, label)) in ticks(&self.axis_y, plot.y).into_iter().zip(self.axis_y.labels()).enumerate() {
+            let center = y as f64 + 0.5;
+            self.lines.push(((left - tick, center), (left - 1f64, center)));
+            if i > 0 {
+                self.grid.push(((first_x, center), (net_right, center)));
+            }
+            let width = font::text_width(&label);
+            self.texts.push(Text::new(&label,
+                                      left_of.saturating_sub(width) as f64,
+                                      y.saturating_sub(H_CHAR / 2) as f64,
+                                      false));
+        }
+    }
+}
+
+impl Text {
+    fn new(text: &str, x: f64, y: f64, vertical: bool) -> Text {
+        Text {
+            position: (x, y),
+            text: String::from(text),
+            width: font::text_width(text),
+            vertical,
+        }
+    }
+}
+
+// pixels of the ticks of `axis` starting at pixel `start`
+fn ticks(axis: &Axis, start: usize) -> Vec<usize> {
+    (0..(axis.interval_count as usize + 1))
+        .map(|i| start + (axis.scale_interval_pix * (i as f64)).round() as usize)
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drawing_test() {
+        let chart = Chart::new(200, 150, "#ffffff", "#000000").unwrap().add_title("Title");
+        let points = vec![(0f64, 0f64), (1f64, 1f64), (2f64, f64::NAN), (3f64, 4f64), (4f64, 2f64)];
+        let serie = Serie::new(points.into_iter(), "#ff0000").unwrap().add_name("serie");
+        let drawing = Drawing::new(&chart, vec![serie].into_iter()).unwrap();
+
+        assert_eq!(drawing.series[0].values, vec![(0f64, 0f64), (1f64, 1f64), (3f64, 4f64), (4f64, 2f64)]);
+        assert_eq!(drawing.series[0].parts.len(), 2);
+        let serie = Serie::new(vec![(0f64, 0f64), (1f64, 1f64), (2f64, f64::NAN), (3f64, 4f64)].into_iter(),
+                               "#ff0000")
+            .unwrap();
+        let dotted = Drawing::new(&chart, vec![serie].into_iter()).unwrap();
+        let dot = &dotted.series[0].parts[1];
+        assert_eq!(dot.len(), 2);
+        assert_eq!(dot[0], dot[1]);
+        assert_eq!(drawing.series[0].color, Color::from("#ff0000"));
+        assert_eq!(drawing.legend.len(), 1);
+        assert!(drawing.texts.iter().any(|t| t.text == "Title"));
+        let intervals = drawing.axis_x.interval_count + drawing.axis_y.interval_count;
+        assert_eq!(drawing.grid.len(), intervals as usize);
+
+        // the corners of the axes land on the corners of the plot
+        let plot = drawing.plot;
+        let (x, y) = drawing.data_position(drawing.axis_x.min_value, drawing.axis_y.max_value);
+        assert_eq!((x, y), (plot.x as f64 + 0.5, plot.top() as f64 + 0.5));
+    }
+}